@@ -0,0 +1,256 @@
+use std::{
+    collections::{HashMap, HashSet},
+    iter,
+    path::Path,
+};
+
+use itertools::Itertools;
+use nanoid::nanoid;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use tower_lsp::lsp_types::{Position, Range, TextEdit, Url, WorkspaceEdit};
+
+use crate::{
+    config::{BlockIdStrategy, Settings},
+    link_format::link_infile_range,
+    vault::{MDIndexedBlock, Reference, Referenceable, Vault},
+};
+
+const RANDOM_ID_ALPHABET: [char; 16] = [
+    'a', 'b', 'c', 'd', 'e', 'f', 'g', '1', '2', '3', '4', '5', '6', '7', '8', '9',
+];
+
+/// The most words of the block text in a slug
+const SLUG_WORDS: usize = 3;
+
+/// Words that are left out of slugs
+const SLUG_STOPWORDS: [&str; 16] = [
+    "a", "an", "and", "are", "as", "at", "by", "for", "in", "is", "of", "on", "or", "the", "to",
+    "with",
+];
+
+/// A new id for a block of the file, in the strategy of the settings, that no other block of the file has
+pub fn new_block_id(vault: &Vault, settings: &Settings, path: &Path, text: &str) -> String {
+    let existing = vault
+        .md_files
+        .get(path)
+        .map(|md_file| {
+            md_file
+                .indexed_blocks
+                .iter()
+                .map(|block| block.index.as_str())
+                .collect::<HashSet<_>>()
+        })
+        .unwrap_or_default();
+
+    block_id(
+        settings.block_id_strategy,
+        settings.block_id_length,
+        text,
+        &existing,
+    )
+}
+
+/// An id that is not one of the existing ids; `length` is the length of random ids. The slug of a text without words
+/// is the next sequential id
+pub fn block_id(
+    strategy: BlockIdStrategy,
+    length: usize,
+    text: &str,
+    existing: &HashSet<&str>,
+) -> String {
+    let sequential = || {
+        let last = existing
+            .iter()
+            .flat_map(|id| id.parse::<usize>().ok())
+            .max()
+            .unwrap_or(0);
+        (last + 1).to_string()
+    };
+
+    match strategy {
+        BlockIdStrategy::Random => {
+            let length = length.max(1);
+
+            iter::repeat_with(|| nanoid!(length, &RANDOM_ID_ALPHABET))
+                .take(100)
+                .find(|id| !existing.contains(id.as_str()))
+                .unwrap_or_else(sequential)
+        }
+        BlockIdStrategy::Slug => {
+            let slug = slug(text);
+            if slug.is_empty() {
+                return sequential();
+            }
+
+            iter::once(slug.clone())
+                .chain((2..).map(|n| format!("{}-{}", slug, n)))
+                .find(|id| !existing.contains(id.as_str()))
+                .unwrap_or(slug)
+        }
+        BlockIdStrategy::Sequential => sequential(),
+    }
+}
+
+/// The first words of the text that are not stopwords, lowercase and joined by '-', without list markers, checkboxes
+/// and quote markers
+fn slug(text: &str) -> String {
+    static MARKUP_RE: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"^(?:[>|\s]*)(?:(?:[-*+]|\d+[.)])\s+)?(?:\[.\]\s+)?").unwrap());
+
+    let text = MARKUP_RE.replace(text, "");
+
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .filter(|word| !SLUG_STOPWORDS.contains(&word.as_str()))
+        .take(SLUG_WORDS)
+        .join("-")
+}
+
+/// Give a block a new id, and update the links to it
+pub fn rename_block_id_edit(
+    vault: &Vault,
+    path: &Path,
+    block: &MDIndexedBlock,
+    new_index: &str,
+) -> Option<WorkspaceEdit> {
+    let (path, _) = vault.md_files.get_key_value(path)?;
+    let references =
+        vault.select_references_for_referenceable(&Referenceable::IndexedBlock(path, block))?;
+
+    let reference_edits = references
+        .into_iter()
+        .filter(|(_, reference)| {
+            matches!(
+                reference,
+                Reference::WikiIndexedBlockLink(..) | Reference::MDIndexedBlockLink(..)
+            )
+        })
+        .flat_map(|(reference_path, reference)| {
+            let line_nr = reference.data().range.start.line;
+            let line = vault.select_line(reference_path, line_nr as isize)?;
+            // the `^index` after the '#', whatever its case in the link
+            let infile = link_infile_range(reference, &line)?;
+
+            Some((
+                reference_path,
+                TextEdit {
+                    range: Range {
+                        start: Position {
+                            line: line_nr,
+                            character: infile.start as u32,
+                        },
+                        end: Position {
+                            line: line_nr,
+                            character: infile.end as u32,
+                        },
+                    },
+                    new_text: format!("^{}", new_index),
+                },
+            ))
+        });
+
+    let block_edit = (
+        path.as_path(),
+        TextEdit {
+            range: *block.range,
+            new_text: format!("^{}", new_index),
+        },
+    );
+
+    let changes = reference_edits
+        .chain(iter::once(block_edit))
+        .into_group_map()
+        .into_iter()
+        .map(|(path, edits)| Some((Url::from_file_path(path).ok()?, edits)))
+        .collect::<Option<HashMap<_, _>>>()?;
+
+    Some(WorkspaceEdit {
+        changes: Some(changes),
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashSet, path::Path};
+
+    use itertools::Itertools;
+
+    use crate::{
+        config::{BlockIdStrategy, Settings},
+        vault::Vault,
+    };
+
+    use super::{block_id, rename_block_id_edit};
+
+    #[test]
+    fn block_id_strategies() {
+        let existing = HashSet::from(["decision-rollout", "2", "notes"]);
+        let text = "- [ ] Decision: rollout of the new pipeline";
+
+        assert_eq!(
+            block_id(BlockIdStrategy::Slug, 8, text, &existing),
+            "decision-rollout-new"
+        );
+        assert_eq!(
+            block_id(BlockIdStrategy::Slug, 8, "Decision rollout", &existing),
+            "decision-rollout-2"
+        );
+        assert_eq!(block_id(BlockIdStrategy::Slug, 8, "> ---", &existing), "3");
+        assert_eq!(
+            block_id(BlockIdStrategy::Sequential, 8, text, &existing),
+            "3"
+        );
+
+        let random = block_id(BlockIdStrategy::Random, 8, text, &existing);
+        assert_eq!(random.len(), 8);
+        assert!(!existing.contains(random.as_str()));
+    }
+
+    #[test]
+    fn renames_block_id_in_links() {
+        let root_dir = Path::new("/vault");
+        let settings = Settings::for_vault(root_dir);
+        let vault = Vault::from_texts(
+            &settings,
+            root_dir,
+            &[
+                ("note.md", "A paragraph ^abc\n"),
+                ("other.md", "[[note#^ABC|see]] and [it](note.md#^abc)\n"),
+            ],
+        );
+        let path = root_dir.join("note.md");
+        let block = &vault.md_files[&path].indexed_blocks[0];
+
+        let edit = rename_block_id_edit(&vault, &path, block, "new").unwrap();
+        let edits = edit
+            .changes
+            .unwrap()
+            .into_iter()
+            .flat_map(|(uri, edits)| {
+                let path = uri.to_file_path().unwrap();
+                let file_name = path.file_name().unwrap().to_string_lossy().to_string();
+                edits.into_iter().map(move |edit| {
+                    (
+                        file_name.clone(),
+                        edit.range.start.character,
+                        edit.range.end.character,
+                        edit.new_text,
+                    )
+                })
+            })
+            .sorted()
+            .collect_vec();
+
+        assert_eq!(
+            edits,
+            vec![
+                ("note.md".to_string(), 12, 16, "^new".to_string()),
+                ("other.md".to_string(), 7, 11, "^new".to_string()),
+                ("other.md".to_string(), 35, 39, "^new".to_string()),
+            ]
+        );
+    }
+}
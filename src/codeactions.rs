@@ -1,17 +1,31 @@
-use std::path::Path;
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
 
+use chrono::{Local, NaiveDate};
+use once_cell::sync::Lazy;
 use pathdiff::diff_paths;
+use regex::Regex;
+use ropey::Rope;
 use tower_lsp::lsp_types::{
-    CodeAction, CodeActionOrCommand, CodeActionParams, CreateFile, CreateFileOptions,
-    DocumentChangeOperation, DocumentChanges, OneOf, OptionalVersionedTextDocumentIdentifier,
-    Position, Range, ResourceOp, TextDocumentEdit, TextEdit, Url, WorkspaceEdit,
+    CodeAction, CodeActionKind, CodeActionOrCommand, CodeActionParams, Command, CreateFile,
+    CreateFileOptions, DocumentChangeOperation, DocumentChanges, OneOf,
+    OptionalVersionedTextDocumentIdentifier, Position, Range, ResourceOp, TextDocumentEdit,
+    TextEdit, Url, WorkspaceEdit,
 };
 
 use crate::{
-    config::Settings,
-    daily::filename_is_formatted,
-    diagnostics::path_unresolved_references,
-    vault::{Reference, Vault},
+    block_ids::{block_id, rename_block_id_edit},
+    config::{BlockIdStrategy, Settings},
+    convert_links::{convert_link, link_style, ConvertLinks, LinkStyle},
+    daily::daily_note_path,
+    diagnostics::{path_ambiguous_references, path_unresolved_references},
+    link_format::{folder_ref_path, link_path, link_path_range, resolve_relative_path},
+    periodic::{new_note_text, note_path, parse_note_name, Period},
+    templates::{list_templates, NewFromTemplate},
+    unique_note::is_unique_note_name,
+    vault::{MDTask, Reference, TaskStatus, Vault},
 };
 
 pub fn code_actions(
@@ -34,6 +48,24 @@ pub fn code_actions(
             && reference.data().range.end.character >= params.range.end.character
     });
 
+    let template_actions = code_action_unresolved
+        .clone()
+        .flat_map(|(_path, reference)| match reference {
+            Reference::WikiFileLink(data) => {
+                template_file_actions(vault, settings, path, &data.reference_text)
+            }
+            _ => vec![],
+        })
+        .collect::<Vec<_>>();
+
+    let task_actions = task_code_actions(vault, params, path, settings);
+
+    let disambiguate_actions = disambiguate_link_actions(vault, params, path, settings);
+
+    let convert_actions = convert_link_actions(vault, params, path, settings);
+
+    let block_id_actions = block_id_actions(vault, params, path, settings);
+
     Some(
         code_action_unresolved
             .flat_map(|(_path, reference)| {
@@ -41,14 +73,7 @@ pub fn code_actions(
                     Reference::WikiFileLink(_data) => {
                         let filename = &reference.data().reference_text;
 
-                        let mut new_path_buf = vault.root_dir().clone();
-                        if filename_is_formatted(settings, filename) {
-                            new_path_buf.push(&settings.daily_notes_folder);
-                        } else {
-                            new_path_buf.push(&settings.new_file_folder_path);
-                        }
-                        new_path_buf.push(filename);
-                        new_path_buf.set_extension("md");
+                        let new_path_buf = new_file_path(vault, settings, path, filename);
 
                         let new_path = Url::from_file_path(&new_path_buf).ok()?;
 
@@ -72,14 +97,7 @@ pub fn code_actions(
                     }
                     Reference::WikiHeadingLink(_data, link_path, heading) => {
 
-                        let mut new_path_buf = vault.root_dir().clone();
-                        if filename_is_formatted(settings, link_path) {
-                            new_path_buf.push(&settings.daily_notes_folder);
-                        } else {
-                            new_path_buf.push(&settings.new_file_folder_path);
-                        }
-                        new_path_buf.push(link_path);
-                        new_path_buf.set_extension("md");
+                        let new_path_buf = new_file_path(vault, settings, path, link_path);
 
                         let new_path = Url::from_file_path(&new_path_buf).ok()?;
 
@@ -144,6 +162,617 @@ pub fn code_actions(
                 }
 
             })
+            .chain(template_actions)
+            .chain(disambiguate_actions)
+            .chain(convert_actions)
+            .chain(block_id_actions)
+            .chain(task_actions)
             .collect(),
     )
 }
+
+/// Convert the link under the cursor to a markdown link or a wikilink, or all the links of the file
+fn convert_link_actions(
+    vault: &Vault,
+    params: &CodeActionParams,
+    path: &Path,
+    settings: &Settings,
+) -> Vec<CodeActionOrCommand> {
+    let Ok(uri) = Url::from_file_path(path) else {
+        return vec![];
+    };
+    let file_names = vault.select_file_names();
+
+    let link_actions = vault
+        .select_references(Some(path))
+        .into_iter()
+        .flatten()
+        .filter(|(_, reference)| {
+            let range = reference.data().range;
+            range.start.line <= params.range.start.line
+                && range.end.line >= params.range.end.line
+                && range.start.character <= params.range.start.character
+                && range.end.character >= params.range.end.character
+        })
+        .flat_map(|(_, reference)| {
+            [LinkStyle::Markdown, LinkStyle::Wiki]
+                .into_iter()
+                .flat_map(|to| {
+                    let new_text = convert_link(vault, settings, file_names, path, reference, to)?;
+
+                    Some(CodeAction {
+                        title: match to {
+                            LinkStyle::Markdown => "Convert to markdown link".to_string(),
+                            LinkStyle::Wiki => "Convert to wikilink".to_string(),
+                        },
+                        kind: Some(CodeActionKind::REFACTOR_REWRITE),
+                        edit: Some(workspace_edit(vec![(
+                            uri.clone(),
+                            vec![TextEdit {
+                                range: *reference.data().range,
+                                new_text,
+                            }],
+                        )])),
+                        ..Default::default()
+                    })
+                })
+                .collect::<Vec<_>>()
+        });
+
+    // the edits of the whole file are only computed when the action is run, by the `convertLinks` command
+    let styles = vault
+        .select_references(Some(path))
+        .into_iter()
+        .flatten()
+        .flat_map(|(_, reference)| link_style(reference))
+        .collect::<Vec<_>>();
+    let file_actions = [LinkStyle::Markdown, LinkStyle::Wiki]
+        .into_iter()
+        .filter(|to| styles.iter().any(|style| style != to))
+        .flat_map(|to| {
+            let title = match to {
+                LinkStyle::Markdown => "Convert all links in the file to markdown links",
+                LinkStyle::Wiki => "Convert all links in the file to wikilinks",
+            };
+            let args = serde_json::to_value(ConvertLinks {
+                to,
+                uri: Some(uri.clone()),
+            })
+            .ok()?;
+
+            Some(CodeAction {
+                title: title.to_string(),
+                kind: Some(CodeActionKind::SOURCE),
+                command: Some(Command {
+                    title: title.to_string(),
+                    command: "convertLinks".into(),
+                    arguments: Some(vec![args]),
+                }),
+                ..Default::default()
+            })
+        });
+
+    link_actions
+        .chain(file_actions)
+        .map(CodeActionOrCommand::CodeAction)
+        .collect()
+}
+
+/// Rename the block id under the cursor to a readable id and update the links to the block: the first words of the block,
+/// or the next number in the file with the sequential block id strategy
+fn block_id_actions(
+    vault: &Vault,
+    params: &CodeActionParams,
+    path: &Path,
+    settings: &Settings,
+) -> Vec<CodeActionOrCommand> {
+    let Some(md_file) = vault.md_files.get(path) else {
+        return vec![];
+    };
+    let strategy = match settings.block_id_strategy {
+        BlockIdStrategy::Sequential => BlockIdStrategy::Sequential,
+        BlockIdStrategy::Random | BlockIdStrategy::Slug => BlockIdStrategy::Slug,
+    };
+
+    md_file
+        .indexed_blocks
+        .iter()
+        .filter(|block| {
+            block.range.start.line == params.range.start.line
+                && block.range.start.character <= params.range.start.character
+                && block.range.end.character >= params.range.start.character
+        })
+        .flat_map(|block| {
+            let text = vault.select_block(path, &block.index)?.text;
+            let others = md_file
+                .indexed_blocks
+                .iter()
+                .map(|other| other.index.as_str())
+                .filter(|other| *other != block.index)
+                .collect::<HashSet<_>>();
+
+            let new_index = block_id(strategy, settings.block_id_length, text, &others);
+            if new_index == block.index {
+                return None;
+            }
+
+            Some(CodeActionOrCommand::CodeAction(CodeAction {
+                title: format!("Rename block ID ^{} to ^{}", block.index, new_index),
+                kind: Some(CodeActionKind::REFACTOR_REWRITE),
+                edit: Some(rename_block_id_edit(vault, path, block, &new_index)?),
+                ..Default::default()
+            }))
+        })
+        .collect()
+}
+
+/// Where a file for an unresolved link is created: periodic notes go in the folder of their period, including any folders
+/// in their format, such as `%Y/%m/%Y-%m-%d`; unique notes go in the unique notes folder and other files in the new file folder.
+/// Relative links, `./note` or `../note`, create the file next to the linking file, and `/folder/note` in the vault root
+fn new_file_path(vault: &Vault, settings: &Settings, from: &Path, filename: &str) -> PathBuf {
+    if let Some((period, date)) = parse_note_name(settings, filename) {
+        return note_path(settings, vault.root_dir(), period, date);
+    }
+
+    let vault_path = match filename.strip_prefix('/') {
+        Some(vault_path) => Some(vault_path.to_string()),
+        None if filename.starts_with("./") || filename.starts_with("../") => {
+            folder_ref_path(vault.root_dir(), from)
+                .and_then(|from_dir| resolve_relative_path(&from_dir, filename))
+        }
+        None => None,
+    };
+    if let Some(vault_path) = vault_path {
+        return vault.root_dir().join(format!("{}.md", vault_path));
+    }
+
+    let mut new_path_buf = vault.root_dir().clone();
+    if is_unique_note_name(settings, filename) {
+        new_path_buf.push(&settings.unique_notes_folder);
+    } else {
+        new_path_buf.push(&settings.new_file_folder_path);
+    }
+    new_path_buf.push(filename);
+    new_path_buf.set_extension("md");
+
+    new_path_buf
+}
+
+/// A Create File action for each template in the templates folder, which fills the new file from that template by
+/// running the `newFromTemplate` command
+fn template_file_actions(
+    vault: &Vault,
+    settings: &Settings,
+    from: &Path,
+    filename: &str,
+) -> Vec<CodeActionOrCommand> {
+    let templates = list_templates(vault.root_dir(), settings);
+    if templates.is_empty() {
+        return vec![];
+    }
+
+    let new_path_buf = new_file_path(vault, settings, from, filename);
+    let Some(relative_path) = diff_paths(&new_path_buf, vault.root_dir()) else {
+        return vec![];
+    };
+
+    templates
+        .into_iter()
+        .flat_map(|template| {
+            let args = NewFromTemplate {
+                template: Some(template.name.clone()),
+                title: relative_path
+                    .file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .map(String::from),
+                folder: relative_path
+                    .parent()
+                    .and_then(|folder| folder.to_str())
+                    .map(String::from),
+                ..Default::default()
+            };
+            let args = serde_json::to_value(args).ok()?;
+
+            let title = format!(
+                "Create File from template {:?}: {:?}",
+                template.name, relative_path
+            );
+
+            Some(CodeActionOrCommand::CodeAction(CodeAction {
+                title: title.clone(),
+                command: Some(Command {
+                    title,
+                    command: "newFromTemplate".into(),
+                    arguments: Some(vec![args]),
+                }),
+                ..Default::default()
+            }))
+        })
+        .collect()
+}
+
+/// For an ambiguous link under the cursor, one action for each of the files that it matches,
+/// replacing the file part of the link with the path to the file in the link format of the settings
+fn disambiguate_link_actions(
+    vault: &Vault,
+    params: &CodeActionParams,
+    path: &Path,
+    settings: &Settings,
+) -> Vec<CodeActionOrCommand> {
+    let file_names = vault.select_file_names();
+    let Some(ambiguous) = path_ambiguous_references(vault, path, file_names) else {
+        return vec![];
+    };
+    let Ok(uri) = Url::from_file_path(path) else {
+        return vec![];
+    };
+
+    ambiguous
+        .into_iter()
+        .filter(|(reference, _)| {
+            let range = reference.data().range;
+            range.start.line <= params.range.start.line
+                && range.end.line >= params.range.end.line
+                && range.start.character <= params.range.start.character
+                && range.end.character >= params.range.end.character
+        })
+        .flat_map(|(reference, matching_paths)| {
+            let Some(line) = vault.select_line(path, reference.data().range.start.line as isize)
+            else {
+                return vec![];
+            };
+            let Some(path_range) = link_path_range(reference, &line) else {
+                return vec![];
+            };
+            let in_angle_brackets = line.get(path_range.start.saturating_sub(1)) == Some(&'<');
+
+            matching_paths
+                .into_iter()
+                .flat_map(|matching_path| {
+                    let target = vault.root_dir().join(format!("{}.md", matching_path));
+                    let new_path =
+                        link_path(settings, file_names, vault.root_dir(), path, &target)?;
+
+                    let new_text = match reference {
+                        Reference::MDFileLink(..)
+                        | Reference::MDHeadingLink(..)
+                        | Reference::MDIndexedBlockLink(..)
+                            if !in_angle_brackets =>
+                        {
+                            new_path.replace(' ', "%20")
+                        }
+                        _ => new_path,
+                    };
+
+                    Some(CodeActionOrCommand::CodeAction(CodeAction {
+                        title: format!("Link to {}.md", matching_path),
+                        kind: Some(CodeActionKind::QUICKFIX),
+                        edit: Some(workspace_edit(vec![(
+                            uri.clone(),
+                            vec![TextEdit {
+                                range: Range {
+                                    start: Position {
+                                        line: reference.data().range.start.line,
+                                        character: path_range.start as u32,
+                                    },
+                                    end: Position {
+                                        line: reference.data().range.start.line,
+                                        character: path_range.end as u32,
+                                    },
+                                },
+                                new_text,
+                            }],
+                        )])),
+                        ..Default::default()
+                    }))
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// The order that the cycle action moves through
+const TASK_STATUS_CYCLE: [TaskStatus; 5] = [
+    TaskStatus::Open,
+    TaskStatus::InProgress,
+    TaskStatus::Done,
+    TaskStatus::Cancelled,
+    TaskStatus::Deferred,
+];
+
+/// Toggle, cycle and migrate actions for the tasks on the lines of the range
+fn task_code_actions(
+    vault: &Vault,
+    params: &CodeActionParams,
+    path: &Path,
+    settings: &Settings,
+) -> Vec<CodeActionOrCommand> {
+    let Some(md_file) = vault.md_files.get(path) else {
+        return vec![];
+    };
+    let Ok(uri) = Url::from_file_path(path) else {
+        return vec![];
+    };
+    let today = Local::now().date_naive();
+
+    md_file
+        .tasks
+        .iter()
+        .filter(|task| {
+            task.range.start.line >= params.range.start.line
+                && task.range.start.line <= params.range.end.line
+        })
+        .flat_map(|task| {
+            let Some(line) = vault.select_line(path, task.range.start.line as isize) else {
+                return vec![];
+            };
+
+            let toggled = match task.status {
+                TaskStatus::Done => TaskStatus::Open,
+                _ => TaskStatus::Done,
+            };
+            let toggle = CodeAction {
+                title: match toggled {
+                    TaskStatus::Done => "Mark task as done".to_string(),
+                    _ => "Mark task as not done".to_string(),
+                },
+                kind: Some(CodeActionKind::REFACTOR_REWRITE),
+                edit: Some(workspace_edit(vec![(
+                    uri.clone(),
+                    set_task_status_edits(task, &line, toggled, today),
+                )])),
+                ..Default::default()
+            };
+
+            let cycled = TASK_STATUS_CYCLE
+                .iter()
+                .position(|status| *status == task.status)
+                .map(|i| TASK_STATUS_CYCLE[(i + 1) % TASK_STATUS_CYCLE.len()])
+                .unwrap_or(TaskStatus::Open);
+            let cycle = CodeAction {
+                title: format!("Cycle task status to [{}]", cycled.to_char()),
+                kind: Some(CodeActionKind::REFACTOR_REWRITE),
+                edit: Some(workspace_edit(vec![(
+                    uri.clone(),
+                    set_task_status_edits(task, &line, cycled, today),
+                )])),
+                ..Default::default()
+            };
+
+            let migrate = migrate_task_action(vault, path, &uri, task, &line, settings, today);
+
+            [Some(toggle), Some(cycle), migrate]
+                .into_iter()
+                .flatten()
+                .map(CodeActionOrCommand::CodeAction)
+                .collect()
+        })
+        .collect()
+}
+
+/// Move an unfinished task to the end of today's daily note, leaving it marked as deferred: `[>]`
+fn migrate_task_action(
+    vault: &Vault,
+    path: &Path,
+    uri: &Url,
+    task: &MDTask,
+    line: &[char],
+    settings: &Settings,
+    today: NaiveDate,
+) -> Option<CodeAction> {
+    if !task.status.is_open() {
+        return None;
+    }
+
+    let daily_note = daily_note_path(settings, vault.root_dir(), today);
+    if daily_note == path {
+        return None;
+    }
+    let daily_note_uri = Url::from_file_path(&daily_note).ok()?;
+
+    let existing_text = vault
+        .ropes
+        .get(&daily_note)
+        .map(|rope| rope.to_string())
+        .or_else(|| std::fs::read_to_string(&daily_note).ok());
+    let daily_note_exists = existing_text.is_some();
+
+    // a new daily note starts with the template, which is written in the same edit as the task
+    let daily_note_text = existing_text.unwrap_or_default();
+    let end = Rope::from_str(&daily_note_text);
+    let end_line = end.len_lines().saturating_sub(1);
+    let end_position = Position {
+        line: end_line as u32,
+        character: end.line(end_line).len_chars() as u32,
+    };
+
+    let migrated_task = format!("- [{}] {}", task.status.to_char(), task.text);
+    let new_text = match daily_note_text.as_str() {
+        _ if !daily_note_exists => {
+            let template =
+                new_note_text(vault.root_dir(), settings, Period::Day, &daily_note, today);
+            match template.as_str() {
+                "" => migrated_task,
+                text if text.ends_with('\n') => format!("{}{}", template, migrated_task),
+                _ => format!("{}\n{}", template, migrated_task),
+            }
+        }
+        "" => migrated_task,
+        text if text.ends_with('\n') => format!("{}\n", migrated_task),
+        _ => format!("\n{}", migrated_task),
+    };
+
+    Some(CodeAction {
+        title: format!(
+            "Migrate task to today's daily note ({})",
+            daily_note.file_stem()?.to_string_lossy()
+        ),
+        kind: Some(CodeActionKind::REFACTOR_REWRITE),
+        edit: Some(WorkspaceEdit {
+            document_changes: Some(DocumentChanges::Operations(vec![
+                DocumentChangeOperation::Op(ResourceOp::Create(CreateFile {
+                    uri: daily_note_uri.clone(),
+                    annotation_id: None,
+                    options: Some(CreateFileOptions {
+                        ignore_if_exists: Some(true),
+                        overwrite: Some(false),
+                    }),
+                })),
+                DocumentChangeOperation::Edit(TextDocumentEdit {
+                    text_document: OptionalVersionedTextDocumentIdentifier {
+                        uri: daily_note_uri,
+                        version: None,
+                    },
+                    edits: vec![OneOf::Left(TextEdit {
+                        range: Range {
+                            start: end_position,
+                            end: end_position,
+                        },
+                        new_text,
+                    })],
+                }),
+                DocumentChangeOperation::Edit(TextDocumentEdit {
+                    text_document: OptionalVersionedTextDocumentIdentifier {
+                        uri: uri.clone(),
+                        version: None,
+                    },
+                    edits: set_task_status_edits(task, line, TaskStatus::Deferred, today)
+                        .into_iter()
+                        .map(OneOf::Left)
+                        .collect(),
+                }),
+            ])),
+            ..Default::default()
+        }),
+        ..Default::default()
+    })
+}
+
+/// Replace the status character; a completion date is stamped when the task becomes done and removed when it is no longer done
+fn set_task_status_edits(
+    task: &MDTask,
+    line: &[char],
+    status: TaskStatus,
+    today: NaiveDate,
+) -> Vec<TextEdit> {
+    static DONE_STAMP_RE: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r" ?✅ ?\d{4}-\d{2}-\d{2}").unwrap());
+
+    let line_number = task.range.start.line;
+    let line_string = String::from_iter(line.iter().filter(|c| **c != '\n' && **c != '\r'));
+    let line_end = line_string.chars().count() as u32;
+
+    let status_edit = TextEdit {
+        range: *task.status_range,
+        new_text: status.to_char().to_string(),
+    };
+
+    let stamp = DONE_STAMP_RE.find(&line_string);
+    let stamp_edit = match (status, stamp) {
+        (TaskStatus::Done, None) => Some(TextEdit {
+            range: Range {
+                start: Position {
+                    line: line_number,
+                    character: line_end,
+                },
+                end: Position {
+                    line: line_number,
+                    character: line_end,
+                },
+            },
+            new_text: format!(" ✅ {}", today.format("%Y-%m-%d")),
+        }),
+        (TaskStatus::Done, Some(_)) => None,
+        (_, Some(stamp)) => Some(TextEdit {
+            range: Range {
+                start: Position {
+                    line: line_number,
+                    character: line_string[..stamp.start()].chars().count() as u32,
+                },
+                end: Position {
+                    line: line_number,
+                    character: line_string[..stamp.end()].chars().count() as u32,
+                },
+            },
+            new_text: String::new(),
+        }),
+        (_, None) => None,
+    };
+
+    std::iter::once(status_edit).chain(stamp_edit).collect()
+}
+
+fn workspace_edit(edits: Vec<(Url, Vec<TextEdit>)>) -> WorkspaceEdit {
+    WorkspaceEdit {
+        document_changes: Some(DocumentChanges::Edits(
+            edits
+                .into_iter()
+                .map(|(uri, edits)| TextDocumentEdit {
+                    text_document: OptionalVersionedTextDocumentIdentifier { uri, version: None },
+                    edits: edits.into_iter().map(OneOf::Left).collect(),
+                })
+                .collect(),
+        )),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use itertools::Itertools;
+    use tower_lsp::lsp_types::{Position, Range, TextEdit};
+
+    use crate::vault::{MDTask, TaskStatus};
+
+    use super::set_task_status_edits;
+
+    #[test]
+    fn done_stamp_is_added_and_removed() {
+        let today = NaiveDate::from_ymd_opt(2026, 10, 18).unwrap();
+        let range = |start, end| Range {
+            start: Position {
+                line: 0,
+                character: start,
+            },
+            end: Position {
+                line: 0,
+                character: end,
+            },
+        };
+
+        let text = "- [ ] task";
+        let task = MDTask::new(text).next().unwrap();
+        let line = text.chars().collect_vec();
+        assert_eq!(
+            set_task_status_edits(&task, &line, TaskStatus::Done, today),
+            vec![
+                TextEdit {
+                    range: range(3, 4),
+                    new_text: "x".into(),
+                },
+                TextEdit {
+                    range: range(10, 10),
+                    new_text: " ✅ 2026-10-18".into(),
+                },
+            ]
+        );
+
+        let text = "- [x] task ✅ 2026-10-17";
+        let task = MDTask::new(text).next().unwrap();
+        let line = text.chars().collect_vec();
+        assert_eq!(
+            set_task_status_edits(&task, &line, TaskStatus::Open, today),
+            vec![
+                TextEdit {
+                    range: range(3, 4),
+                    new_text: " ".into(),
+                },
+                TextEdit {
+                    range: range(10, 23),
+                    new_text: "".into(),
+                },
+            ]
+        );
+    }
+}
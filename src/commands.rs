@@ -1,23 +1,29 @@
+use std::collections::HashMap;
 use std::fs::File;
-use std::path::Path;
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
 
 use crate::config::Settings;
+use crate::daily::{formatted_note_path, Direction};
+use crate::periodic::{create_note, parse_relative_name, Period};
+use crate::templates::{list_templates, untitled_note_path, NewFromTemplate};
+use crate::unique_note::{
+    create_unique_note, unique_note_file_names, unique_note_prefix, NewUniqueNote,
+};
 use chrono::offset::Local;
-use chrono::NaiveDateTime;
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
 use fuzzydate::parse;
+use serde::Deserialize;
 use serde_json::Value;
 use tower_lsp::jsonrpc::{Error, Result};
-use tower_lsp::lsp_types::{MessageType, ShowDocumentParams, Url};
+use tower_lsp::lsp_types::{MessageActionItem, MessageType, ShowDocumentParams, Url};
 
 fn datetime_to_file(
     datetime: NaiveDateTime,
     dailynote_format: &str,
     root_dir: &Path,
 ) -> Option<Url> {
-    let filename = datetime.format(dailynote_format).to_string();
-    let path = root_dir.join(&filename);
-
-    Url::from_file_path(path.with_extension("md")).ok()
+    Url::from_file_path(formatted_note_path(dailynote_format, root_dir, datetime)).ok()
 }
 
 pub async fn jump(
@@ -27,28 +33,26 @@ pub async fn jump(
     jump_to: Option<&str>,
 ) -> Result<Option<Value>> {
     // if jump_to is None, use the current time.
+    // `this week`, `last month`, ... jump to the other periodic notes; anything else is a day
 
-    let daily_note_format = &settings.dailynote;
-    let daily_note_path = root_dir.join(&settings.daily_notes_folder);
-    let note_file = match jump_to {
-        Some(jmp_str) => parse(jmp_str)
-            .ok()
-            .and_then(|dt| datetime_to_file(dt, &daily_note_format, &daily_note_path)),
-        None => datetime_to_file(
-            Local::now().naive_local(),
-            &daily_note_format,
-            &daily_note_path,
-        ),
+    let now = Local::now().naive_local();
+    let (period, datetime) = match jump_to {
+        Some(jmp_str) => match parse_relative_name(jmp_str, now.date()) {
+            Some((period, date)) => (period, Some(NaiveDateTime::new(date, NaiveTime::MIN))),
+            None => (Period::Day, parse(jmp_str).ok()),
+        },
+        None => (Period::Day, Some(now)),
     };
+    let note_format = period.format(settings);
+    let note_folder = root_dir.join(period.folder(settings));
+    let note_file = datetime.and_then(|dt| datetime_to_file(dt, note_format, &note_folder));
 
-    if let Some(uri) = note_file {
+    if let (Some(uri), Some(datetime)) = (note_file, datetime) {
         // file creation can fail and return an Err, ignore this and try
         // to open the file on the off chance the client knows what to do
         // TODO: log failure to create file
         let _ = uri.to_file_path().map(|path| {
-            path.parent().map(|parent| std::fs::create_dir_all(parent));
-
-            let _ = File::create_new(path.as_path().to_owned());
+            let _ = create_note(root_dir, settings, period, &path, datetime.date());
         });
 
         client
@@ -74,6 +78,192 @@ pub async fn jump(
     }
 }
 
+/// Create a note from a template in the templates folder and open it; the client is asked to choose a template if none is given
+pub async fn new_from_template(
+    client: &tower_lsp::Client,
+    root_dir: &Path,
+    settings: &Settings,
+    args: NewFromTemplate,
+) -> Result<Option<Value>> {
+    let templates = list_templates(root_dir, settings);
+
+    if templates.is_empty() {
+        return Err(Error::invalid_params(format!(
+            "There are no templates in the templates folder ({:?})",
+            settings.templates_folder
+        )));
+    }
+
+    let template_name = match args.template {
+        Some(template_name) => template_name,
+        None => {
+            let actions = templates
+                .iter()
+                .map(|template| MessageActionItem {
+                    title: template.name.clone(),
+                    properties: HashMap::new(),
+                })
+                .collect();
+
+            match client
+                .show_message_request(MessageType::INFO, "New note from template", Some(actions))
+                .await?
+            {
+                Some(chosen) => chosen.title,
+                None => return Ok(None), // dismissed
+            }
+        }
+    };
+
+    let template = templates
+        .iter()
+        .find(|template| template.name == template_name)
+        .ok_or_else(|| Error::invalid_params(format!("No template named {template_name:?}")))?;
+
+    let folder = args
+        .folder
+        .as_ref()
+        .unwrap_or(&settings.new_file_folder_path);
+    if let Some(outside) = [Some(folder), args.title.as_ref()]
+        .into_iter()
+        .flatten()
+        .find(|path| !is_in_vault(Path::new(path)))
+    {
+        return Err(Error::invalid_params(format!(
+            "{outside:?} is not a path in the vault"
+        )));
+    }
+
+    let folder = root_dir.join(folder);
+    let path = match &args.title {
+        Some(title) => folder.join(format!("{}.md", title)),
+        None => untitled_note_path(&folder),
+    };
+    let title = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or_default();
+
+    let content = template
+        .content(title, Local::now().naive_local(), &args.variables)
+        .ok_or_else(|| {
+            Error::invalid_params(format!("Could not read template {template_name:?}"))
+        })?;
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| Error::invalid_params(format!("Could not create {parent:?}: {e}")))?;
+    }
+    File::create_new(&path)
+        .and_then(|mut file| file.write_all(content.as_bytes()))
+        .map_err(|e| Error::invalid_params(format!("Could not create {path:?}: {e}")))?;
+
+    let uri = Url::from_file_path(&path).map_err(|_| Error::internal_error())?;
+
+    client
+        .show_document(ShowDocumentParams {
+            uri,
+            external: Some(false),
+            take_focus: Some(true),
+            selection: None,
+        })
+        .await
+        .map(|success| Some(success.into()))
+}
+
+/// A relative path without `..`, which can not lead out of the vault
+fn is_in_vault(path: &Path) -> bool {
+    path.components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+}
+
+/// Create a unique note, `202610171230 Title`, in the unique notes folder and open it
+pub async fn new_unique_note(
+    client: &tower_lsp::Client,
+    root_dir: &Path,
+    settings: &Settings,
+    args: NewUniqueNote,
+) -> Result<Option<Value>> {
+    let now = Local::now().naive_local();
+    let prefix = match args.prefix {
+        Some(prefix) => prefix,
+        None => unique_note_prefix(settings, now, &unique_note_file_names(root_dir, settings))
+            .ok_or_else(|| {
+                Error::invalid_params(format!(
+                    "Invalid unique note prefix format {:?}",
+                    settings.unique_note_prefix
+                ))
+            })?,
+    };
+
+    let path = create_unique_note(root_dir, settings, &prefix, args.title.as_deref(), now)
+        .map_err(|e| Error::invalid_params(format!("Could not create the unique note: {e}")))?;
+
+    let uri = Url::from_file_path(&path).map_err(|_| Error::internal_error())?;
+
+    if args.open == Some(false) {
+        return Ok(Some(uri.to_string().into()));
+    }
+
+    client
+        .show_document(ShowDocumentParams {
+            uri,
+            external: Some(false),
+            take_focus: Some(true),
+            selection: None,
+        })
+        .await
+        .map(|success| Some(success.into()))
+}
+
+/// Arguments of the `previousDailyNote` and `nextDailyNote` commands
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AdjacentDailyNote {
+    /// The daily note to move from
+    pub uri: Url,
+    /// Open the note of the adjacent day, creating it if it does not exist, instead of the nearest existing note
+    #[serde(default)]
+    pub create: bool,
+}
+
+/// Open the daily note found by `daily::adjacent_daily_note`, creating it if necessary
+pub async fn open_adjacent_daily_note(
+    client: &tower_lsp::Client,
+    root_dir: &Path,
+    settings: &Settings,
+    direction: Direction,
+    adjacent: Option<(PathBuf, NaiveDate)>,
+) -> Result<Option<Value>> {
+    let Some((path, date)) = adjacent else {
+        client
+            .show_message(
+                MessageType::INFO,
+                match direction {
+                    Direction::Previous => "There is no previous daily note",
+                    Direction::Next => "There is no next daily note",
+                },
+            )
+            .await;
+        return Ok(None);
+    };
+
+    // the note usually exists already; see `jump`
+    let _ = create_note(root_dir, settings, Period::Day, &path, date);
+
+    let uri = Url::from_file_path(&path).map_err(|_| Error::internal_error())?;
+
+    client
+        .show_document(ShowDocumentParams {
+            uri,
+            external: Some(false),
+            take_focus: Some(true),
+            selection: None,
+        })
+        .await
+        .map(|success| Some(success.into()))
+}
+
 // tests
 #[cfg(test)]
 mod tests {
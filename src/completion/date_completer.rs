@@ -0,0 +1,227 @@
+use std::path::Path;
+
+use chrono::{Local, NaiveDate};
+use fuzzydate::parse;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use tower_lsp::lsp_types::{
+    CompletionItem, CompletionItemKind, CompletionItemLabelDetails, CompletionTextEdit, Position,
+    Range, TextEdit,
+};
+
+use crate::{
+    config::Settings,
+    link_format::{format_link, link_path, FileNames},
+    periodic::{note_path, parse_relative_name, Period},
+    vault::Vault,
+};
+
+use super::{util::check_in_code_block, Completable, Completer, Context};
+
+/// Completes `@tomorrow`, `@next monday`, `@last week` ... to a link to the periodic note of the date.
+/// After a due date marker, `📅 @tomorrow` or `due:: @tomorrow`, the date itself is inserted instead
+pub struct DateCompleter<'a> {
+    settings: &'a Settings,
+    vault: &'a Vault,
+    path: &'a Path,
+    file_names: &'a FileNames,
+    /// The text after the '@'
+    query: String,
+    line: u32,
+    /// The character of the '@'
+    start: u32,
+    character: u32,
+    in_due_date: bool,
+}
+
+/// The relative names that are always offered; other dates can be typed out for fuzzydate, as in `@in 3 days`
+const NAMES: [&str; 26] = [
+    "today",
+    "tomorrow",
+    "yesterday",
+    "next monday",
+    "next tuesday",
+    "next wednesday",
+    "next thursday",
+    "next friday",
+    "next saturday",
+    "next sunday",
+    "last monday",
+    "last tuesday",
+    "last wednesday",
+    "last thursday",
+    "last friday",
+    "last saturday",
+    "last sunday",
+    "this week",
+    "next week",
+    "last week",
+    "this month",
+    "next month",
+    "last month",
+    "this year",
+    "next year",
+    "last year",
+];
+
+/// The part of the line before the cursor that is being completed: (character of the '@', text after it, in a due date marker)
+fn date_query(line_before_cursor: &str) -> Option<(usize, &str, bool)> {
+    static DATE_QUERY_RE: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"(?<due>(📅|due::) *)?(?<at>@)(?<query>[\w ]*)$").unwrap());
+
+    let captures = DATE_QUERY_RE.captures(line_before_cursor)?;
+    let at = captures.name("at")?;
+
+    let in_due_date = captures.name("due").is_some();
+
+    // `@` in emails such as `name@example.com` is not a date
+    let preceding = line_before_cursor[..at.start()].chars().last();
+    if !in_due_date && preceding.is_some_and(|c| !c.is_whitespace() && !"([".contains(c)) {
+        return None;
+    }
+
+    Some((
+        line_before_cursor[..at.start()].chars().count(),
+        captures.name("query")?.as_str(),
+        in_due_date,
+    ))
+}
+
+impl<'a> Completer<'a> for DateCompleter<'a> {
+    fn construct(context: Context<'a>, line: usize, character: usize) -> Option<Self>
+    where
+        Self: Sized + Completer<'a>,
+    {
+        if check_in_code_block(&context, line, character) {
+            return None;
+        }
+
+        let line_chars = context.vault.select_line(context.path, line as isize)?;
+        let line_before_cursor = String::from_iter(line_chars.get(..character)?);
+
+        let (start, query, in_due_date) = date_query(&line_before_cursor)?;
+
+        Some(DateCompleter {
+            settings: context.settings,
+            vault: context.vault,
+            path: context.path,
+            file_names: context.vault.select_file_names(),
+            query: query.to_string(),
+            line: line as u32,
+            start: start as u32,
+            character: character as u32,
+            in_due_date,
+        })
+    }
+
+    fn completions(&self) -> Vec<impl Completable<'a, Self>>
+    where
+        Self: Sized,
+    {
+        let today = Local::now().date_naive();
+        let query = self.query.trim().to_lowercase();
+
+        // a typed date that is not one of the names comes first
+        let typed = (!query.is_empty() && !NAMES.iter().any(|name| name.starts_with(&query)))
+            .then_some(query.as_str());
+
+        typed
+            .into_iter()
+            .chain(NAMES)
+            .flat_map(|name| {
+                let (period, date) = parse_relative_name(name, today)
+                    .or_else(|| Some((Period::Day, parse(name).ok()?.date())))?;
+
+                Some(DateCompletion {
+                    name: name.to_string(),
+                    period,
+                    date,
+                })
+            })
+            .filter(|completion| !self.in_due_date || completion.period == Period::Day)
+            .collect()
+    }
+
+    type FilterParams = String;
+    fn completion_filter_text(&self, params: Self::FilterParams) -> String {
+        format!("@{}", params)
+    }
+}
+
+struct DateCompletion {
+    name: String,
+    period: Period,
+    date: NaiveDate,
+}
+
+impl<'a> Completable<'a, DateCompleter<'a>> for DateCompletion {
+    fn completions(&self, completer: &DateCompleter<'a>) -> Option<CompletionItem> {
+        let (new_text, kind) = match completer.in_due_date {
+            true => (
+                self.date.format("%Y-%m-%d").to_string(),
+                CompletionItemKind::VALUE,
+            ),
+            false => {
+                let root_dir = completer.vault.root_dir();
+                let note_path = note_path(completer.settings, root_dir, self.period, self.date);
+                let link_path = link_path(
+                    completer.settings,
+                    completer.file_names,
+                    root_dir,
+                    completer.path,
+                    &note_path,
+                )?;
+
+                (
+                    format_link(completer.settings, &link_path, None, None),
+                    CompletionItemKind::REFERENCE,
+                )
+            }
+        };
+
+        Some(CompletionItem {
+            label: format!("@{}", self.name),
+            label_details: Some(CompletionItemLabelDetails {
+                detail: None,
+                description: Some(new_text.clone()),
+            }),
+            kind: Some(kind),
+            text_edit: Some(CompletionTextEdit::Edit(TextEdit {
+                range: Range {
+                    start: Position {
+                        line: completer.line,
+                        character: completer.start,
+                    },
+                    end: Position {
+                        line: completer.line,
+                        character: completer.character,
+                    },
+                },
+                new_text,
+            })),
+            filter_text: Some(completer.completion_filter_text(self.name.clone())),
+            ..Default::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::date_query;
+
+    #[test]
+    fn parse_date_query() {
+        assert_eq!(date_query("meet @next mon"), Some((5, "next mon", false)));
+        assert_eq!(date_query("@"), Some((0, "", false)));
+        assert_eq!(
+            date_query("- [ ] task 📅 @tomorrow"),
+            Some((13, "tomorrow", true))
+        );
+        assert_eq!(
+            date_query("- [ ] task [due:: @tod"),
+            Some((18, "tod", true))
+        );
+        assert_eq!(date_query("mail me@example"), None);
+        assert_eq!(date_query("@today, then"), None);
+    }
+}
@@ -1,13 +1,10 @@
 use std::path::Path;
 
-use tower_lsp::lsp_types::{CompletionItem, CompletionItemKind, Documentation};
+use tower_lsp::lsp_types::{CompletionItem, CompletionItemKind};
 
-use crate::{
-    ui::preview_referenceable,
-    vault::{MDFootnote, Preview, Referenceable, Vault},
-};
+use crate::vault::{MDFootnote, Preview, Referenceable, Vault};
 
-use super::{Completable, Completer};
+use super::{completion_data, Completable, Completer};
 
 use rayon::prelude::*;
 
@@ -90,8 +87,7 @@ impl<'a> Completable<'a, FootnoteCompleter<'a>> for FootnoteCompletion<'a> {
         Some(CompletionItem {
             label: refname.to_string(),
             kind: Some(CompletionItemKind::REFERENCE),
-            documentation: preview_referenceable(completer.vault, &self_referenceable)
-                .map(Documentation::MarkupContent),
+            data: completion_data(completer.vault, &self_referenceable),
             filter_text: Some(completer.completion_filter_text((refname, self_referenceable))),
             ..Default::default()
         })
@@ -0,0 +1,107 @@
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use crate::{config::Settings, vault::Vault};
+
+/// Ranks the notes that are being worked on first: recently modified notes, opened notes, and often linked notes.
+/// The score is added to the fuzzy match score, so the weights are in the units of nucleo scores; a matched character is worth about 16
+pub struct Frecency<'a> {
+    settings: &'a Settings,
+    opened_files: HashSet<&'a Path>,
+    vault: &'a Vault,
+    now: SystemTime,
+    scores: RefCell<HashMap<PathBuf, u32>>,
+}
+
+/// Days for the recency score to halve
+const RECENCY_HALF_LIFE_DAYS: f64 = 7.0;
+
+impl<'a> Frecency<'a> {
+    pub fn new(
+        vault: &'a Vault,
+        opened_files: &'a [PathBuf],
+        settings: &'a Settings,
+    ) -> Frecency<'a> {
+        Frecency {
+            settings,
+            opened_files: opened_files.iter().map(PathBuf::as_path).collect(),
+            vault,
+            now: SystemTime::now(),
+            scores: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// The score of the note at the path; unresolved notes only score for their links
+    pub fn score(&self, path: &Path) -> u32 {
+        if let Some(score) = self.scores.borrow().get(path) {
+            return *score;
+        }
+
+        let settings = self.settings;
+
+        let age_days = self
+            .vault
+            .select_modified_time(path)
+            .and_then(|modified| self.now.duration_since(modified).ok())
+            .map(|age| age.as_secs_f64() / 86400.0);
+        let recency = match age_days {
+            Some(age_days) => 0.5_f64.powf(age_days / RECENCY_HALF_LIFE_DAYS),
+            None => 0.0,
+        };
+
+        let opened = match self.opened_files.contains(path) {
+            true => 1.0,
+            false => 0.0,
+        };
+
+        let link_count = self.vault.select_backlink_count(path);
+
+        let score = settings.frecency_recency_weight as f64 * recency
+            + settings.frecency_opened_weight as f64 * opened
+            + settings.frecency_backlinks_weight as f64 * (1.0 + link_count as f64).log2();
+
+        let score = score.round() as u32;
+        self.scores.borrow_mut().insert(path.to_path_buf(), score);
+
+        score
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::{config::Settings, vault::Vault};
+
+    use super::Frecency;
+
+    #[test]
+    fn frecency_scores() {
+        let root_dir = Path::new("/vault");
+        let settings = Settings::for_vault(root_dir);
+        let vault = Vault::from_texts(
+            &settings,
+            root_dir,
+            &[
+                ("folder/linked.md", ""),
+                ("note.md", "[[linked]] and [it](folder/linked.md#Heading)"),
+            ],
+        );
+
+        let linked = root_dir.join("folder/linked.md");
+        let note = root_dir.join("note.md");
+        let unlinked = root_dir.join("No Such Note.md");
+        let opened = [unlinked.clone()];
+
+        let frecency = Frecency::new(&vault, &opened, &settings);
+
+        // both notes were just changed; only the linked one has links to it
+        assert_eq!(frecency.score(&note), settings.frecency_recency_weight);
+        assert!(frecency.score(&linked) > frecency.score(&note));
+        assert_eq!(frecency.score(&unlinked), settings.frecency_opened_weight);
+    }
+}
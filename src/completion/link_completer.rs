@@ -1,29 +1,34 @@
 use std::{
     collections::HashSet,
+    fmt::{self, Display},
     iter::once,
     path::{Path, PathBuf},
     time::SystemTime,
 };
 
-use chrono::{Duration, NaiveDate};
+use chrono::{Duration, Local, NaiveDate};
 use itertools::Itertools;
 use once_cell::sync::Lazy;
 use rayon::prelude::*;
 use regex::Regex;
 use tower_lsp::lsp_types::{
-    CompletionItem, CompletionItemKind, CompletionItemLabelDetails, CompletionTextEdit,
-    Documentation, InsertTextFormat, Position, Range, TextEdit,
+    Command, CompletionItem, CompletionItemKind, CompletionItemLabelDetails, CompletionTextEdit,
+    InsertTextFormat, Position, Range, TextEdit,
 };
 
 use crate::{
     completion::util::check_in_code_block,
     config::Settings,
-    ui::preview_referenceable,
+    link_format::{link_path, markdown_link, FileNames},
+    periodic::{note_path, parse_note_path, relative_name, Period},
+    unique_note::{unique_note_name, unique_note_prefix},
     vault::{MDFile, MDHeading, Reference, Referenceable, Vault},
 };
 
 use super::{
-    matcher::{fuzzy_match_completions, Matchable, OrderedCompletion},
+    completion_data,
+    frecency::Frecency,
+    matcher::{weighted_fuzzy_match_completions, Matchable, OrderedCompletion},
     Completable, Completer, Context,
 };
 
@@ -44,15 +49,84 @@ pub struct MarkdownLinkCompleter<'a> {
     pub vault: &'a Vault,
     pub context_path: &'a Path,
     pub settings: &'a Settings,
+    pub opened_files: &'a [PathBuf],
 }
 
 pub trait LinkCompleter<'a>: Completer<'a> {
     fn settings(&self) -> &'a Settings;
+    fn opened_files(&self) -> &'a [PathBuf];
     fn completion_text_edit(&self, display: Option<&str>, refname: &str) -> CompletionTextEdit;
     fn entered_refname(&self) -> String;
     fn vault(&self) -> &'a Vault;
     fn position(&self) -> Position;
     fn path(&self) -> &'a Path;
+    /// Fuzzy match the link completions, ranking the notes with a higher frecency first
+    fn match_link_completions(
+        &self,
+        filter_text: &str,
+        link_completions: Vec<LinkCompletion<'a>>,
+    ) -> Vec<OrderedCompletion<'a, Self, LinkCompletion<'a>>>
+    where
+        LinkCompletion<'a>: Completable<'a, Self>,
+    {
+        let frecency = Frecency::new(self.vault(), self.opened_files(), self.settings());
+
+        let matches = weighted_fuzzy_match_completions(
+            filter_text,
+            link_completions,
+            &self.settings().case_matching,
+            |completion| match completion.path() {
+                Some(path) => frecency.score(path),
+                None => 0,
+            },
+        );
+
+        match matches.is_empty() {
+            true => self
+                .new_zettel_completion()
+                .map(|new_zettel| OrderedCompletion::new(new_zettel, format!("{:06}", 0)))
+                .into_iter()
+                .collect(),
+            false => matches,
+        }
+    }
+
+    /// A new unique note, titled with the entered refname, for a query that matches no note
+    fn new_zettel_completion(&self) -> Option<LinkCompletion<'a>> {
+        let entered_refname = self.entered_refname();
+        let title = entered_refname.trim();
+        if title.is_empty() || title.contains('#') || self.settings().unique_note_prefix.is_empty()
+        {
+            return None;
+        }
+
+        let vault = self.vault();
+        let root_dir = vault.root_dir();
+        let folder = root_dir.join(&self.settings().unique_notes_folder);
+
+        // the files of the vault are known, so the prefix is unique without reading the folder
+        let existing = vault
+            .md_files
+            .keys()
+            .filter(|path| path.parent() == Some(folder.as_path()))
+            .flat_map(|path| path.file_name()?.to_str().map(String::from))
+            .collect::<Vec<_>>();
+        let prefix = unique_note_prefix(self.settings(), Local::now().naive_local(), &existing)?;
+        let new_path = folder.join(format!("{}.md", unique_note_name(&prefix, Some(title))));
+
+        Some(NewZettel {
+            match_string: title.to_string(),
+            ref_name: link_path(
+                self.settings(),
+                vault.select_file_names(),
+                root_dir,
+                self.path(),
+                &new_path,
+            )?,
+            prefix,
+        })
+    }
+
     fn link_completions(&self) -> Vec<LinkCompletion<'a>>
     where
         Self: Sync,
@@ -86,6 +160,7 @@ pub trait LinkCompleter<'a>: Completer<'a> {
         });
 
         let heading_completions = self.settings().heading_completions;
+        let file_names = self.vault().select_file_names();
 
         // Get and filter referenceables
         let completions = referenceables
@@ -99,7 +174,7 @@ pub trait LinkCompleter<'a>: Completer<'a> {
                     )
             })
             .flat_map(|referenceable| {
-                LinkCompletion::new(referenceable.clone(), self)
+                LinkCompletion::new(referenceable.clone(), self, file_names)
                     .into_iter()
                     .par_bridge()
             })
@@ -112,11 +187,18 @@ pub trait LinkCompleter<'a>: Completer<'a> {
             .map(|completion| completion.refname())
             .collect::<HashSet<_>>();
 
-        // Get daily notes for convienience
+        // Get daily notes for convienience, and this, last and next week, month and year
         let today = chrono::Local::now().date_naive();
         let days = (-7..=7)
             .flat_map(|i| Some(today + Duration::try_days(i)?))
-            .flat_map(|date| MDDailyNote::from_date(date, self))
+            .map(|date| (Period::Day, date))
+            .chain(
+                [Period::Week, Period::Month, Period::Year]
+                    .into_iter()
+                    .cartesian_product(-1..=1)
+                    .flat_map(|(period, n)| Some((period, period.offset(today, n)?))),
+            )
+            .flat_map(|(period, date)| MDDailyNote::from_date(period, date, self, file_names))
             .filter(|date| !refnames.contains(&date.ref_name))
             .map(LinkCompletion::DailyNote);
 
@@ -129,6 +211,10 @@ impl<'a> LinkCompleter<'a> for MarkdownLinkCompleter<'a> {
         self.settings
     }
 
+    fn opened_files(&self) -> &'a [PathBuf] {
+        self.opened_files
+    }
+
     fn path(&self) -> &'a Path {
         self.context_path
     }
@@ -185,7 +271,7 @@ impl<'a> Completer<'a> for MarkdownLinkCompleter<'a> {
     where
         Self: Sized,
     {
-        if context.settings.references_in_codeblocks == false
+        if !context.settings.references_in_codeblocks
             && check_in_code_block(&context, line, character)
         {
             return None;
@@ -193,7 +279,7 @@ impl<'a> Completer<'a> for MarkdownLinkCompleter<'a> {
 
         let Context {
             vault,
-            opened_files: _,
+            opened_files,
             path,
             ..
         } = context;
@@ -260,6 +346,7 @@ impl<'a> Completer<'a> for MarkdownLinkCompleter<'a> {
             vault,
             context_path: context.path,
             settings: context.settings,
+            opened_files,
         });
 
         partial
@@ -277,10 +364,7 @@ impl<'a> Completer<'a> for MarkdownLinkCompleter<'a> {
 
         let link_completions = self.link_completions();
 
-        let matches =
-            fuzzy_match_completions(&filter_text, link_completions, &self.settings.case_matching);
-
-        matches
+        self.match_link_completions(&filter_text, link_completions)
     }
 
     /// The completions refname
@@ -300,11 +384,11 @@ pub enum PartialInfileRef {
     BlockRef(String),
 }
 
-impl ToString for PartialInfileRef {
-    fn to_string(&self) -> String {
+impl Display for PartialInfileRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Self::HeadingRef(string) => string.to_owned(),
-            Self::BlockRef(string) => format!("^{}", string),
+            Self::HeadingRef(string) => write!(f, "{}", string),
+            Self::BlockRef(string) => write!(f, "^{}", string),
         }
     }
 }
@@ -335,6 +419,10 @@ impl<'a> LinkCompleter<'a> for WikiLinkCompleter<'a> {
         self.settings
     }
 
+    fn opened_files(&self) -> &'a [PathBuf] {
+        self.files
+    }
+
     fn path(&self) -> &'a Path {
         self.context_path
     }
@@ -354,7 +442,34 @@ impl<'a> LinkCompleter<'a> for WikiLinkCompleter<'a> {
         String::from_iter(&self.cmp_text)
     }
 
+    /// With `use_markdown_links`, the whole `[[link]]` is replaced with a markdown link
     fn completion_text_edit(&self, display: Option<&str>, refname: &str) -> CompletionTextEdit {
+        let end = Position {
+            line: self.line,
+            character: (self.chars_in_line - 1).min(self.character + 2_u32), // TODO: in zed, you cannot zed end to be out of the line count index
+        };
+
+        if self.settings().use_markdown_links {
+            let (path, infile_ref) = match refname.split_once('#') {
+                Some((path, infile_ref)) => (path, Some(infile_ref)),
+                None => (refname, None),
+            };
+
+            return CompletionTextEdit::Edit(TextEdit {
+                range: Range {
+                    start: Position {
+                        line: self.line,
+                        character: self.index - 1, // the first '[' in [[link]]
+                    },
+                    end,
+                },
+                new_text: format!(
+                    "{}${{2:}}",
+                    markdown_link(self.settings(), path, infile_ref, display)
+                ),
+            });
+        }
+
         let ext = if self.settings().include_md_extension_wikilink {
             ".md"
         } else {
@@ -366,10 +481,7 @@ impl<'a> LinkCompleter<'a> for WikiLinkCompleter<'a> {
                     line: self.line,
                     character: self.index + 1_u32, // index is right at the '[' in [[link]]; we want one more than that
                 },
-                end: Position {
-                    line: self.line,
-                    character: (self.chars_in_line - 1).min(self.character + 2_u32), // TODO: in zed, you cannot zed end to be out of the line count index
-                },
+                end,
             },
 
             new_text: format!(
@@ -389,7 +501,7 @@ impl<'a> Completer<'a> for WikiLinkCompleter<'a> {
     where
         Self: Sized,
     {
-        if context.settings.references_in_codeblocks == false
+        if !context.settings.references_in_codeblocks
             && check_in_code_block(&context, line, character)
         {
             return None;
@@ -446,6 +558,8 @@ impl<'a> Completer<'a> for WikiLinkCompleter<'a> {
     {
         let WikiLinkCompleter { vault, .. } = self;
 
+        let file_names = vault.select_file_names();
+
         match *self.cmp_text {
             // Give recent referenceables; TODO: improve this;
             [] => self
@@ -481,7 +595,9 @@ impl<'a> Completer<'a> for WikiLinkCompleter<'a> {
                     Some(
                         referenceables
                             .into_iter()
-                            .flat_map(move |referenceable| LinkCompletion::new(referenceable, self))
+                            .flat_map(|referenceable| {
+                                LinkCompletion::new(referenceable, self, file_names)
+                            })
                             .flatten()
                             .flat_map(move |completion| {
                                 Some(OrderedCompletion::<WikiLinkCompleter, LinkCompletion>::new(
@@ -498,13 +614,7 @@ impl<'a> Completer<'a> for WikiLinkCompleter<'a> {
 
                 let link_completions = self.link_completions();
 
-                let matches = fuzzy_match_completions(
-                    &String::from_iter(filter_text),
-                    link_completions,
-                    &self.settings.case_matching,
-                );
-
-                matches
+                self.match_link_completions(&String::from_iter(filter_text), link_completions)
             }
             _ => vec![],
         }
@@ -524,7 +634,8 @@ pub enum LinkCompletion<'a> {
         referenceable: Referenceable<'a>,
     },
     Alias {
-        filename: &'a str,
+        /// The shortest unambiguous path of the file
+        filename: String,
         match_string: &'a str,
         referenceable: Referenceable<'a>,
     },
@@ -544,53 +655,76 @@ pub enum LinkCompletion<'a> {
         referenceable: Referenceable<'a>,
     },
     DailyNote(MDDailyNote<'a>),
+    /// A unique note, `202610171230 Title`, that is created when the completion is accepted
+    NewZettel {
+        /// The entered title
+        match_string: String,
+        ref_name: String,
+        prefix: String,
+    },
 }
 
 use LinkCompletion::*;
 
 impl LinkCompletion<'_> {
+    /// Files, headings and blocks are completed with the path to their file in the link format of the settings;
+    /// the shortest path is the file name unless other files have the same name
     fn new<'a>(
         referenceable: Referenceable<'a>,
         completer: &impl LinkCompleter<'a>,
+        file_names: &FileNames,
     ) -> Option<Vec<LinkCompletion<'a>>> {
-        if let Some(daily) = MDDailyNote::from_referenceable(referenceable.clone(), completer) {
+        let root_dir = completer.vault().root_dir();
+        let link_path = |path: &Path| {
+            link_path(
+                completer.settings(),
+                file_names,
+                root_dir,
+                completer.path(),
+                path,
+            )
+        };
+
+        if let Some(daily) =
+            MDDailyNote::from_referenceable(referenceable.clone(), completer, file_names)
+        {
             Some(vec![DailyNote(daily)])
         } else {
             match referenceable {
-                Referenceable::File(_, mdfile) => {
+                Referenceable::File(path, mdfile) => {
+                    let file_path = link_path(path)?;
+
                     Some(
                         once(File {
                             mdfile,
-                            match_string: mdfile.file_name()?.to_string(),
+                            match_string: file_path.clone(),
                             referenceable: referenceable.clone(),
                         })
-                        .chain(mdfile.metadata.iter().flat_map(|it| it.aliases()).flat_map(
-                            |alias| {
-                                Some(Alias {
-                                    filename: mdfile.file_name()?,
+                        .chain(
+                            mdfile
+                                .metadata
+                                .iter()
+                                .flat_map(|it| it.aliases())
+                                .map(|alias| Alias {
+                                    filename: file_path.clone(),
                                     match_string: alias,
                                     referenceable: referenceable.clone(),
-                                })
-                            },
-                        ))
+                                }),
+                        )
                         .collect(),
                     )
                 }
                 Referenceable::Heading(path, mdheading) => Some(
                     once(Heading {
                         heading: mdheading,
-                        match_string: format!(
-                            "{}#{}",
-                            path.file_stem()?.to_str()?,
-                            mdheading.heading_text
-                        ),
+                        match_string: format!("{}#{}", link_path(path)?, mdheading.heading_text),
                         referenceable,
                     })
                     .collect(),
                 ),
                 Referenceable::IndexedBlock(path, indexed) => Some(
                     once(Block {
-                        match_string: format!("{}#^{}", path.file_stem()?.to_str()?, indexed.index),
+                        match_string: format!("{}#^{}", link_path(path)?, indexed.index),
                         referenceable,
                     })
                     .collect(),
@@ -636,8 +770,9 @@ impl LinkCompletion<'_> {
             | Self::Heading { referenceable, .. }
             | Self::Block { referenceable, .. }
             | Self::Unresolved { referenceable, .. }
-            | Self::Alias { referenceable, .. } => referenceable.to_owned(),
-            Self::DailyNote(daily) => daily.referenceable(completer),
+            | Self::Alias { referenceable, .. } => Some(referenceable.to_owned()),
+            Self::DailyNote(daily) => Some(daily.referenceable(completer)),
+            Self::NewZettel { .. } => None,
         };
 
         let label = self.match_string();
@@ -654,6 +789,7 @@ impl LinkCompletion<'_> {
                 } => CompletionItemKind::KEYWORD,
                 Self::Alias { .. } => CompletionItemKind::ENUM,
                 Self::DailyNote { .. } => CompletionItemKind::EVENT,
+                Self::NewZettel { .. } => CompletionItemKind::FILE,
             }),
             label_details: match self {
                 Self::Unresolved {
@@ -672,25 +808,64 @@ impl LinkCompletion<'_> {
                 Heading { .. } => None,
                 Block { .. } => None,
                 DailyNote(_) => None,
+                NewZettel { ref_name, .. } => Some(CompletionItemLabelDetails {
+                    detail: Some("New zettel".into()),
+                    description: Some(format!("{}.md", ref_name)),
+                }),
             },
             text_edit: Some(text_edit),
             preselect: Some(match self {
                 Self::DailyNote(daily) => {
-                    daily.relative_name(completer) == Some(completer.entered_refname())
+                    daily.relative_name() == Some(completer.entered_refname())
                 }
+                Self::NewZettel { .. } => false,
                 link_completion => link_completion.refname() == completer.entered_refname(),
             }),
             filter_text: Some(filter_text.to_string()),
-            documentation: preview_referenceable(vault, &referenceable)
-                .map(Documentation::MarkupContent),
+            data: referenceable.and_then(|referenceable| completion_data(vault, &referenceable)),
+            // the note is created without opening it, so that the cursor stays in the link
+            command: match self {
+                Self::NewZettel {
+                    match_string,
+                    prefix,
+                    ..
+                } => Some(Command {
+                    title: "Create new zettel".into(),
+                    command: "newUniqueNote".into(),
+                    arguments: Some(vec![serde_json::json!({
+                        "title": match_string,
+                        "prefix": prefix,
+                        "open": false,
+                    })]),
+                }),
+                _ => None,
+            },
             ..Default::default()
         }
     }
 
+    /// The file of the completion, for its frecency; unresolved files have the path they would be created at
+    fn path(&self) -> Option<&Path> {
+        match self {
+            File { referenceable, .. }
+            | Alias { referenceable, .. }
+            | Heading { referenceable, .. }
+            | Block { referenceable, .. }
+            | Unresolved { referenceable, .. } => Some(referenceable.get_path()),
+            DailyNote(daily) => daily
+                .real_referenceaable
+                .as_ref()
+                .map(|referenceable| referenceable.get_path()),
+            NewZettel { .. } => None,
+        }
+    }
+
     /// Refname to be inserted into the document
     fn refname(&self) -> String {
         match self {
-            Self::DailyNote(MDDailyNote { ref_name, .. }) => ref_name.to_string(),
+            Self::DailyNote(MDDailyNote { ref_name, .. }) | Self::NewZettel { ref_name, .. } => {
+                ref_name.to_string()
+            }
             File { match_string, .. }
             | Heading { match_string, .. }
             | Block { match_string, .. }
@@ -720,7 +895,8 @@ impl<'a> Completable<'a, MarkdownLinkCompleter<'a>> for LinkCompletion<'a> {
                 match_string: _, ..
             } => None,
             Self::Alias { match_string, .. } => Some(match_string.to_string()),
-            Self::DailyNote(daily) => daily.relative_name(markdown_link_completer),
+            Self::DailyNote(daily) => daily.relative_name(),
+            Self::NewZettel { match_string, .. } => Some(match_string.to_string()),
             Self::Heading {
                 heading,
                 match_string: _,
@@ -778,6 +954,7 @@ impl<'a> Completable<'a, WikiLinkCompleter<'a>> for LinkCompletion<'a> {
             Block { .. } => None,
             Unresolved { .. } => None,
             DailyNote(_) => None,
+            NewZettel { match_string, .. } => Some(format!("${{1:{}}}", match_string)),
         };
 
         let text_edit = completer.completion_text_edit(wikilink_display_text.as_deref(), &refname);
@@ -807,92 +984,91 @@ impl Matchable for LinkCompletion<'_> {
             }
             | Block { match_string, .. }
             | Unresolved { match_string, .. }
+            | NewZettel { match_string, .. }
             | DailyNote(MDDailyNote { match_string, .. }) => match_string,
             Alias { match_string, .. } => match_string,
         }
     }
 }
 
+/// A daily note, or a note of another period, with a name relative to today: `tomorrow`, `next week`, `last month`
 #[derive(Clone, Debug)]
 pub struct MDDailyNote<'a> {
     match_string: String,
     ref_name: String,
+    period: Period,
+    date: NaiveDate,
     real_referenceaable: Option<Referenceable<'a>>,
 }
 
 impl MDDailyNote<'_> {
-    pub fn relative_name<'a>(&self, completer: &impl LinkCompleter<'a>) -> Option<String> {
-        let self_date = self.get_self_date(completer)?;
-
-        Self::relative_date_string(self_date)
-    }
-
-    pub fn get_self_date<'a>(&self, completer: &impl LinkCompleter<'a>) -> Option<NaiveDate> {
-        let dailynote_format = &completer.settings().dailynote;
-
-        chrono::NaiveDate::parse_from_str(&self.ref_name, dailynote_format).ok()
-    }
-
-    fn relative_date_string(date: NaiveDate) -> Option<String> {
+    pub fn relative_name(&self) -> Option<String> {
         let today = chrono::Local::now().date_naive();
 
-        if today == date {
-            Some("today".to_string())
-        } else {
-            match (date - today).num_days() {
-                1 => Some("tomorrow".to_string()),
-                2..=7 => Some(format!("next {}", date.format("%A"))),
-                -1 => Some("yesterday".to_string()),
-                -7..=-1 => Some(format!("last {}", date.format("%A"))),
-                _ => None,
-            }
-        }
+        relative_name(self.period, self.date, today)
     }
 
     /// The refname used for fuzzy matching a completion - not the actual inserted text
     fn from_referenceable<'a>(
         referenceable: Referenceable<'a>,
         completer: &impl LinkCompleter<'a>,
+        file_names: &FileNames,
     ) -> Option<MDDailyNote<'a>> {
-        let Some((filerefname, filter_refname)) = (match referenceable {
-            Referenceable::File(&ref path, _) | Referenceable::UnresovledFile(ref path, _) => {
-                let filename = path.file_name();
-                let dailynote_format = &completer.settings().dailynote;
-                let (date, filename) = filename.and_then(|filename| {
-                    let filename = filename.to_str()?;
-                    let filename = filename.replace(".md", "");
-                    Some((
-                        chrono::NaiveDate::parse_from_str(&filename, dailynote_format).ok(),
-                        filename,
-                    ))
-                })?;
-
-                date.and_then(Self::relative_date_string)
-                    .map(|thing| (filename.clone(), format!("{}: {}", thing, filename)))
+        let root_dir = completer.vault().root_dir();
+        let (filename, ref_name, period, date) = match referenceable {
+            Referenceable::File(path, _) => {
+                let filename = path.file_name()?.to_str()?.replace(".md", "");
+                let (period, date) = parse_note_path(completer.settings(), root_dir, path)?;
+                let ref_name = link_path(
+                    completer.settings(),
+                    file_names,
+                    root_dir,
+                    completer.path(),
+                    path,
+                )?;
+
+                (filename, ref_name, period, date)
             }
-            _ => None,
-        }) else {
-            return None;
+            Referenceable::UnresovledFile(ref path, _) => {
+                let filename = path.file_name()?.to_str()?.replace(".md", "");
+                let (period, date) = parse_note_path(completer.settings(), root_dir, path)?;
+
+                (filename.clone(), filename, period, date)
+            }
+            _ => return None,
         };
 
+        let today = chrono::Local::now().date_naive();
+        let relative = relative_name(period, date, today)?;
+
         Some(MDDailyNote {
-            match_string: filter_refname,
-            ref_name: filerefname,
+            match_string: format!("{}: {}", relative, filename),
+            ref_name,
+            period,
+            date,
             real_referenceaable: Some(referenceable),
         })
     }
 
     fn from_date<'a>(
+        period: Period,
         date: NaiveDate,
         completer: &impl LinkCompleter<'a>,
+        file_names: &FileNames,
     ) -> Option<MDDailyNote<'a>> {
-        let filerefname = date.format(&completer.settings().dailynote).to_string();
-        let match_string = format!("{}: {}", Self::relative_date_string(date)?, filerefname);
+        let today = chrono::Local::now().date_naive();
+        let settings = completer.settings();
+        let root_dir = completer.vault().root_dir();
+        let path = note_path(settings, root_dir, period, date);
+        let filerefname = path.file_stem()?.to_str()?;
+        let match_string = format!("{}: {}", relative_name(period, date, today)?, filerefname);
 
         // path on unresolved file is useless
         Some(MDDailyNote {
             match_string,
-            ref_name: filerefname.clone(),
+            ref_name: link_path(settings, file_names, root_dir, completer.path(), &path)?,
+            period,
+            date,
             real_referenceaable: None,
         })
     }
@@ -903,8 +1079,12 @@ impl MDDailyNote<'_> {
             return referencaable.clone();
         }
 
-        let mut path = completer.vault().root_dir().to_path_buf();
-        path.push(format!("{}.md", self.ref_name));
+        let path = note_path(
+            completer.settings(),
+            completer.vault().root_dir(),
+            self.period,
+            self.date,
+        );
 
         let unresolved_file = Referenceable::UnresovledFile(path.to_path_buf(), &self.ref_name);
 
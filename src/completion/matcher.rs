@@ -1,4 +1,6 @@
-use std::ops::Deref;
+use std::{cmp::Reverse, ops::Deref};
+
+use itertools::Itertools;
 
 use nucleo_matcher::{
     pattern::{self, Normalization},
@@ -68,15 +70,35 @@ pub fn fuzzy_match_completions<'a, 'b, C: Completer<'a>, T: Matchable + Completa
     items: impl IntoIterator<Item = T>,
     case: &Case,
 ) -> Vec<OrderedCompletion<'a, C, T>> {
-    let normal_fuzzy_match = fuzzy_match(filter_text, items, case);
+    weighted_fuzzy_match_completions(filter_text, items, case, |_| 0)
+}
 
-    normal_fuzzy_match
+/// Fuzzy match with a bonus added to the score of each match. The sort text is the zero-padded rank,
+/// as clients sort it as a string
+pub fn weighted_fuzzy_match_completions<
+    'a,
+    'b,
+    C: Completer<'a>,
+    T: Matchable + Completable<'a, C>,
+>(
+    filter_text: &'b str,
+    items: impl IntoIterator<Item = T>,
+    case: &Case,
+    bonus: impl Fn(&T) -> u32,
+) -> Vec<OrderedCompletion<'a, C, T>> {
+    fuzzy_match(filter_text, items, case)
         .into_iter()
-        .map(|(item, score)| OrderedCompletion::new(item, score.to_string()))
+        .map(|(item, score)| {
+            let score = score.saturating_add(bonus(&item));
+            (item, score)
+        })
+        .sorted_by_key(|(_, score)| Reverse(*score))
+        .enumerate()
+        .map(|(rank, (item, _))| OrderedCompletion::new(item, format!("{:06}", rank)))
         .collect::<Vec<_>>()
 }
 
-pub fn fuzzy_match<'a, T: Matchable>(
+pub fn fuzzy_match<T: Matchable>(
     filter_text: &str,
     items: impl IntoIterator<Item = T>,
     case: &Case,
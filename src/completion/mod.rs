@@ -1,10 +1,19 @@
 use std::path::{Path, PathBuf};
 
-use tower_lsp::lsp_types::{CompletionItem, CompletionList, CompletionParams, CompletionResponse};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tower_lsp::lsp_types::{
+    CompletionItem, CompletionList, CompletionParams, CompletionResponse, Documentation,
+};
 
-use crate::{config::Settings, vault::Vault};
+use crate::{
+    config::Settings,
+    ui::preview_referenceable,
+    vault::{MDTag, Referenceable, Vault},
+};
 
 use self::callout_completer::CalloutCompleter;
+use self::date_completer::DateCompleter;
 use self::link_completer::WikiLinkCompleter;
 use self::{
     footnote_completer::FootnoteCompleter, link_completer::MarkdownLinkCompleter,
@@ -12,7 +21,9 @@ use self::{
 };
 
 mod callout_completer;
+mod date_completer;
 mod footnote_completer;
+mod frecency;
 mod link_completer;
 mod matcher;
 mod tag_completer;
@@ -91,6 +102,13 @@ pub fn get_completions(
             params.text_document_position.position.character,
         )
     })
+    .or_else(|| {
+        run_completer::<DateCompleter>(
+            completion_context,
+            params.text_document_position.position.line,
+            params.text_document_position.position.character,
+        )
+    })
     .or_else(|| {
         run_completer::<TagCompleter>(
             completion_context,
@@ -114,6 +132,83 @@ pub fn get_completions(
     })
 }
 
+/// The `data` of a completion item, from which its documentation is computed on `completionItem/resolve`;
+/// previews with backlinks are too slow to compute for every item of a completion list
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CompletionData {
+    pub path: PathBuf,
+    pub refname: String,
+}
+
+/// The `data` for the completion item of a referenceable
+pub fn completion_data(vault: &Vault, referenceable: &Referenceable) -> Option<Value> {
+    serde_json::to_value(CompletionData {
+        path: referenceable.get_path().to_path_buf(),
+        refname: referenceable.get_refname(vault.root_dir())?.full_refname,
+    })
+    .ok()
+}
+
+/// Add the documentation to a completion item that carries `CompletionData`
+pub fn resolve_completion(vault: &Vault, item: CompletionItem) -> CompletionItem {
+    let Some(data) = item
+        .data
+        .clone()
+        .and_then(|data| serde_json::from_value::<CompletionData>(data).ok())
+    else {
+        return item;
+    };
+
+    let documentation = match vault
+        .select_referenceable_nodes(Some(&data.path))
+        .into_iter()
+        .find(|referenceable| {
+            referenceable
+                .get_refname(vault.root_dir())
+                .is_some_and(|refname| refname.full_refname == data.refname)
+        }) {
+        Some(referenceable) => preview_referenceable(vault, &referenceable),
+        // a tag previews all of its nested tags, so a level such as `#project` of `#project/alpha`,
+        // which no file has by itself, has a preview too
+        None if data.refname.starts_with('#') => {
+            let md_tag = MDTag {
+                tag_ref: data.refname.trim_start_matches('#').to_string(),
+                range: Default::default(),
+            };
+
+            preview_referenceable(vault, &Referenceable::Tag(&data.path, &md_tag))
+        }
+        // unresolved references have no file; the refname is `file`, `file#heading` or `file#^index`
+        None => {
+            let (file, infile_ref) = match data.refname.split_once('#') {
+                Some((file, infile_ref)) => (file.to_string(), Some(infile_ref.to_string())),
+                None => (data.refname.clone(), None),
+            };
+            let index = infile_ref
+                .as_deref()
+                .and_then(|infile_ref| infile_ref.strip_prefix('^'))
+                .map(String::from);
+
+            let referenceable = match (&infile_ref, &index) {
+                (_, Some(index)) => {
+                    Referenceable::UnresovledIndexedBlock(data.path.clone(), &file, index)
+                }
+                (Some(heading), None) => {
+                    Referenceable::UnresolvedHeading(data.path.clone(), &file, heading)
+                }
+                (None, _) => Referenceable::UnresovledFile(data.path.clone(), &file),
+            };
+
+            preview_referenceable(vault, &referenceable)
+        }
+    };
+
+    CompletionItem {
+        documentation: documentation.map(Documentation::MarkupContent),
+        ..item
+    }
+}
+
 // #[cfg(test)]
 // mod tests {
 //     use itertools::Itertools;
@@ -331,3 +426,75 @@ fn run_completer<'a, T: Completer<'a>>(
         items: completions,
     }))
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use tower_lsp::lsp_types::{CompletionItem, Documentation};
+
+    use crate::{
+        config::Settings,
+        vault::{Referenceable, Vault},
+    };
+
+    use super::{completion_data, resolve_completion, CompletionData};
+
+    #[test]
+    fn resolves_documentation() {
+        let root_dir = Path::new("/vault");
+        let settings = Settings::for_vault(root_dir);
+        let vault = Vault::from_texts(
+            &settings,
+            root_dir,
+            &[
+                (
+                    "note.md",
+                    "# First Heading\n\nSome text #tag\n\n[[missing]]\n",
+                ),
+                ("other.md", "No tags\n"),
+            ],
+        );
+
+        let referenceables = vault.select_referenceable_nodes(None);
+        let heading = referenceables
+            .iter()
+            .find(|referenceable| match referenceable {
+                Referenceable::Heading(_, heading) => heading.heading_text == "First Heading",
+                _ => false,
+            })
+            .unwrap();
+        let unresolved = referenceables
+            .iter()
+            .find(|referenceable| matches!(referenceable, Referenceable::UnresovledFile(..)))
+            .unwrap();
+
+        for referenceable in [heading, unresolved] {
+            let item = CompletionItem {
+                data: completion_data(&vault, referenceable),
+                ..Default::default()
+            };
+            assert!(item.documentation.is_none());
+            assert!(resolve_completion(&vault, item).documentation.is_some());
+        }
+
+        let item = resolve_completion(&vault, CompletionItem::default());
+        assert!(item.documentation.is_none());
+
+        // a tag from a file that does not have it
+        let item = CompletionItem {
+            data: serde_json::to_value(CompletionData {
+                path: root_dir.join("other.md"),
+                refname: "#tag".into(),
+            })
+            .ok(),
+            ..Default::default()
+        };
+        let Some(Documentation::MarkupContent(documentation)) =
+            resolve_completion(&vault, item).documentation
+        else {
+            panic!("the tag has no documentation")
+        };
+        assert!(documentation.value.starts_with("`Tag:` `#tag`"));
+    }
+}
@@ -1,20 +1,20 @@
-use std::path::Path;
+use std::path::PathBuf;
 
 use itertools::Itertools;
 use once_cell::sync::Lazy;
 use regex::Regex;
 use tower_lsp::lsp_types::{
-    CompletionItem, CompletionItemKind, CompletionItemLabelDetails, CompletionTextEdit,
-    Documentation, Position, Range, TextEdit,
+    CompletionItem, CompletionItemKind, CompletionItemLabelDetails, CompletionTextEdit, Position,
+    Range, TextEdit,
 };
 
 use crate::{
     completion::util::check_in_code_block,
-    ui,
     vault::{MDTag, Referenceable, Vault},
 };
 
 use super::{
+    completion_data,
     matcher::{fuzzy_match_completions, Matchable},
     Completable, Completer, Context, LineRange,
 };
@@ -36,9 +36,7 @@ impl<'a> Completer<'a> for TagCompleter<'a> {
     where
         Self: Sized + Completer<'a>,
     {
-        if context.settings.tags_in_codeblocks == false
-            && check_in_code_block(&context, line, character)
-        {
+        if !context.settings.tags_in_codeblocks && check_in_code_block(&context, line, character) {
             return None;
         }
 
@@ -83,26 +81,73 @@ impl<'a> Completer<'a> for TagCompleter<'a> {
             .vault
             .select_referenceable_nodes(None)
             .into_par_iter()
-            .flat_map(TagCompletable::from_referenceable)
-            .filter(|tag| {
-                !(tag.tag.1.range.start.line <= self.line as u32
-                    && tag.tag.1.range.start.character <= self.character as u32
-                    && tag.tag.1.range.end.line >= self.line as u32
-                    && tag.tag.1.range.end.character >= self.character as u32)
+            .flat_map(|referenceable| match referenceable {
+                Referenceable::Tag(path, tag) => Some((path, tag)),
+                _ => None,
+            })
+            .filter(|(_, tag)| {
+                !(tag.range.start.line <= self.line as u32
+                    && tag.range.start.character <= self.character as u32
+                    && tag.range.end.line >= self.line as u32
+                    && tag.range.end.character >= self.character as u32)
             })
             .collect::<Vec<_>>();
 
-        // uniqued
-        let tag_referenceables = tag_referenceables
+        // complete one level at a time: #parent/ch completes the children of #parent
+        let parent_level = self
+            .inputted_tag
+            .0
+            .rsplit_once('/')
+            .map(|(parent, _)| parent);
+
+        let tag_levels = tag_referenceables
+            .iter()
+            .flat_map(|(path, tag)| {
+                MDTag::tag_paths(&tag.tag_ref)
+                    .filter(|level| {
+                        level.rsplit_once('/').map(|(parent, _)| parent) == parent_level
+                    })
+                    .map(move |level| (level, *path, *tag))
+            })
+            .into_group_map_by(|(level, _, _)| *level);
+
+        let tag_completables = tag_levels
             .into_iter()
-            .unique_by(|tag| tag.match_string().to_owned())
+            .map(|(level, tags)| {
+                let children = tag_referenceables
+                    .iter()
+                    .flat_map(|(_, tag)| {
+                        tag.tag_ref
+                            .strip_prefix(level)?
+                            .strip_prefix('/')?
+                            .split('/')
+                            .next()
+                    })
+                    .unique()
+                    .count();
+
+                // prefer an occurrence of the level itself so that the preview has a location
+                let (_, path, tag) = tags
+                    .iter()
+                    .find(|(_, _, tag)| tag.tag_ref == level)
+                    .unwrap_or(&tags[0]);
+
+                TagCompletable {
+                    path: path.to_path_buf(),
+                    tag: MDTag {
+                        tag_ref: level.to_string(),
+                        range: tag.range,
+                    },
+                    children,
+                }
+            })
             .collect::<Vec<_>>();
 
         let filter_text = &self.inputted_tag.0;
 
         let filtered = fuzzy_match_completions(
             filter_text,
-            tag_referenceables,
+            tag_completables,
             &self.context.settings.case_matching,
         );
 
@@ -116,29 +161,24 @@ impl<'a> Completer<'a> for TagCompleter<'a> {
     }
 }
 
-struct TagCompletable<'a> {
-    tag: (&'a Path, &'a MDTag),
-}
-
-impl TagCompletable<'_> {
-    fn from_referenceable(referenceable: Referenceable<'_>) -> Option<TagCompletable<'_>> {
-        match referenceable {
-            Referenceable::Tag(path, tag) => Some(TagCompletable { tag: (path, tag) }),
-            _ => None,
-        }
-    }
+struct TagCompletable {
+    path: PathBuf,
+    /// The tag up to the completed level
+    tag: MDTag,
+    /// Number of distinct tags nested directly under this level
+    children: usize,
 }
 
-impl Matchable for TagCompletable<'_> {
+impl Matchable for TagCompletable {
     fn match_string(&self) -> &str {
-        &self.tag.1.tag_ref
+        &self.tag.tag_ref
     }
 }
 
-impl<'a> Completable<'a, TagCompleter<'a>> for TagCompletable<'a> {
+impl<'a> Completable<'a, TagCompleter<'a>> for TagCompletable {
     fn completions(&self, completer: &TagCompleter<'a>) -> Option<CompletionItem> {
         let text_edit = CompletionTextEdit::Edit(TextEdit {
-            new_text: format!("#{}", self.tag.1.tag_ref),
+            new_text: format!("#{}", self.tag.tag_ref),
             range: Range {
                 start: Position {
                     line: completer.line as u32,
@@ -151,9 +191,7 @@ impl<'a> Completable<'a, TagCompleter<'a>> for TagCompletable<'a> {
             },
         });
 
-        let path = self.tag.0;
-        let path_buf = path.to_path_buf();
-        let self_as_referenceable = Referenceable::Tag(&path_buf, self.tag.1);
+        let self_as_referenceable = Referenceable::Tag(&self.path, &self.tag);
 
         let num_references = completer
             .vault
@@ -162,17 +200,20 @@ impl<'a> Completable<'a, TagCompleter<'a>> for TagCompletable<'a> {
             .unwrap_or(0);
 
         Some(CompletionItem {
-            label: self.tag.1.tag_ref.clone(),
+            label: self.tag.tag_ref.clone(),
             kind: Some(CompletionItemKind::KEYWORD),
-            filter_text: Some(completer.completion_filter_text(&self.tag.1.tag_ref.clone())),
-            documentation: ui::preview_referenceable(completer.vault, &self_as_referenceable)
-                .map(Documentation::MarkupContent),
+            filter_text: Some(completer.completion_filter_text(&self.tag.tag_ref.clone())),
+            data: completion_data(completer.vault, &self_as_referenceable),
             label_details: Some(CompletionItemLabelDetails {
                 detail: Some(match num_references {
                     1 => "1 reference".to_string(),
                     n => format!("{} references", n),
                 }),
-                description: None,
+                description: match self.children {
+                    0 => None,
+                    1 => Some("1 nested tag".to_string()),
+                    n => Some(format!("{} nested tags", n)),
+                },
             }),
             text_edit: Some(text_edit),
             ..Default::default()
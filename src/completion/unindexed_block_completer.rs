@@ -1,25 +1,30 @@
 use itertools::Itertools;
 use rayon::prelude::*;
+use serde_json::Value;
 use tower_lsp::lsp_types::{
     Command, CompletionItem, CompletionItemKind, CompletionItemLabelDetails, Documentation,
-    InsertTextFormat, MarkupContent, MarkupKind, Position, Range, TextEdit, Url,
+    InsertTextFormat, MarkupContent, MarkupKind, Url,
 };
 
 use crate::{
-    ui::preview_referenceable,
-    vault::{get_obsidian_ref_path, Block, Referenceable},
+    block_ids::new_block_id,
+    link_format::{link_path, FileNames},
+    vault::{Block, Referenceable},
 };
-use nanoid::nanoid;
 
 use super::{
+    completion_data,
     link_completer::{LinkCompleter, MarkdownLinkCompleter, WikiLinkCompleter},
     matcher::{fuzzy_match_completions, Matchable},
     Completable, Completer,
 };
 
+/// The most blocks from the block index to fuzzy match; the completion list is incomplete, so the client asks again as the query grows
+const CANDIDATE_LIMIT: usize = 200;
+
 pub struct UnindexedBlockCompleter<'a, T: LinkCompleter<'a>> {
     link_completer: T,
-    new_id: String,
+    file_names: &'a FileNames,
     __phantom: std::marker::PhantomData<&'a T>,
 }
 
@@ -33,29 +38,28 @@ impl<'a, C: LinkCompleter<'a>> UnindexedBlockCompleter<'a, C> {
     }
 
     fn new(completer: C) -> Self {
-        let rand_id = nanoid!(
-            5,
-            &['a', 'b', 'c', 'd', 'e', 'f', 'g', '1', '2', '3', '4', '5', '6', '7', '8', '9']
-        );
-
         Self {
+            file_names: completer.vault().select_file_names(),
             link_completer: completer,
-            new_id: rand_id,
             __phantom: std::marker::PhantomData,
         }
     }
 
     fn completables(&self) -> Vec<UnindexedBlock<'a>> {
-        let blocks = self.link_completer.vault().select_blocks();
+        let blocks = self
+            .link_completer
+            .vault()
+            .select_blocks(self.grep_match_text().trim(), CANDIDATE_LIMIT);
         let position = self.link_completer.position();
+        let path = self.link_completer.path();
 
         blocks
             .into_par_iter()
             .filter(|block| {
-                !(block.range.start.line <= position.line
-                    && block.range.start.character <= position.character
-                    && block.range.end.line >= position.line
-                    && block.range.end.character >= position.character)
+                // not the block that is being written
+                !(block.file == path
+                    && block.range.start.line <= position.line
+                    && block.range.end.line >= position.line)
             })
             .map(UnindexedBlock)
             .collect::<Vec<_>>()
@@ -140,17 +144,27 @@ impl<'a> UnindexedBlock<'a> {
         &self,
         completer: &'a UnindexedBlockCompleter<'a, T>,
     ) -> Option<(String, CompletionItem)> {
-        let rand_id = &completer.new_id;
-
-        let path_ref =
-            get_obsidian_ref_path(completer.link_completer.vault().root_dir(), self.0.file)?;
+        let path_ref = link_path(
+            completer.link_completer.settings(),
+            completer.file_names,
+            completer.link_completer.vault().root_dir(),
+            completer.link_completer.path(),
+            self.0.file,
+        )?;
         let url = Url::from_file_path(self.0.file).ok()?;
 
         let block = self.0;
+        let new_id = new_block_id(
+            completer.link_completer.vault(),
+            completer.link_completer.settings(),
+            block.file,
+            block.text,
+        );
 
         // check if the block is already indexed
-        let (documentation, command, kind, label_detail, refname): (
+        let (documentation, data, command, kind, label_detail, refname): (
             Option<Documentation>,
+            Option<Value>,
             Option<Command>,
             CompletionItemKind,
             Option<CompletionItemLabelDetails>,
@@ -162,13 +176,13 @@ impl<'a> UnindexedBlock<'a> {
             .into_par_iter()
             .find_any(|referenceable| match referenceable {
                 Referenceable::IndexedBlock(_path, indexed_block) => {
-                    indexed_block.range.start.line == block.range.start.line
+                    block.id == Some(indexed_block.index.as_str())
                 }
                 _ => false,
             }) {
             Some(ref referenceable @ Referenceable::IndexedBlock(_, indexed_block)) => (
-                preview_referenceable(completer.link_completer.vault(), referenceable)
-                    .map(Documentation::MarkupContent),
+                None,
+                completion_data(completer.link_completer.vault(), referenceable),
                 None,
                 CompletionItemKind::REFERENCE,
                 Some(CompletionItemLabelDetails {
@@ -181,7 +195,7 @@ impl<'a> UnindexedBlock<'a> {
                 Some(Documentation::MarkupContent(MarkupContent {
                     kind: MarkupKind::Markdown,
                     value: (block.range.start.line as isize - 1
-                        ..=block.range.start.line as isize + 1)
+                        ..=block.range.end.line as isize + 1)
                         .flat_map(|i| {
                             Some((
                                 completer
@@ -192,7 +206,9 @@ impl<'a> UnindexedBlock<'a> {
                             ))
                         })
                         .map(|(iter, ln)| {
-                            if ln == block.range.start.line as isize {
+                            if (block.range.start.line as isize..=block.range.end.line as isize)
+                                .contains(&ln)
+                            {
                                 format!("**{}**\n", String::from_iter(iter).trim())
                                 // highlight the block to be references
                             } else {
@@ -201,30 +217,16 @@ impl<'a> UnindexedBlock<'a> {
                         })
                         .join(""),
                 })),
+                None,
                 Some(Command {
                     title: "Insert Block Reference Into File".into(),
                     command: "apply_edits".into(),
                     arguments: Some(vec![serde_json::to_value(
                         tower_lsp::lsp_types::WorkspaceEdit {
                             changes: Some(
-                                vec![(
-                                    url,
-                                    vec![TextEdit {
-                                        range: Range {
-                                            start: Position {
-                                                line: block.range.end.line,
-                                                character: block.range.end.character - 1,
-                                            },
-                                            end: Position {
-                                                line: block.range.end.line,
-                                                character: block.range.end.character - 1,
-                                            },
-                                        },
-                                        new_text: format!("   ^{}", rand_id),
-                                    }],
-                                )]
-                                .into_iter()
-                                .collect(),
+                                vec![(url, vec![block.id_placement.edit(&new_id)])]
+                                    .into_iter()
+                                    .collect(),
                             ),
                             change_annotations: None,
                             document_changes: None,
@@ -233,8 +235,11 @@ impl<'a> UnindexedBlock<'a> {
                     .ok()?]),
                 }),
                 CompletionItemKind::TEXT,
-                None,
-                format!("{}#^{}", path_ref, rand_id),
+                Some(CompletionItemLabelDetails {
+                    detail: Some(block.kind.name().to_string()),
+                    description: None,
+                }),
+                format!("{}#^{}", path_ref, new_id),
             ),
         };
 
@@ -243,6 +248,7 @@ impl<'a> UnindexedBlock<'a> {
             CompletionItem {
                 label: block.text.to_string(),
                 documentation,
+                data,
                 // Insert the index for the block
                 command,
                 kind: Some(kind),
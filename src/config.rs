@@ -14,10 +14,33 @@ pub struct Settings {
     /// Diffrent pages path than default
     pub new_file_folder_path: String,
     pub daily_notes_folder: String,
+    /// Path of the template for new daily notes, relative to the vault root; empty for no template
+    pub daily_note_template: String,
+    /// Folder of the note templates, relative to the vault root
+    pub templates_folder: String,
+    /// Format of the prefix of unique notes, as in `202610171230 Title`
+    pub unique_note_prefix: String,
+    pub unique_notes_folder: String,
+    pub unique_note_template: String,
+    /// Format of weekly notes
+    pub weeklynote: String,
+    pub weekly_notes_folder: String,
+    pub weekly_note_template: String,
+    /// Format of monthly notes
+    pub monthlynote: String,
+    pub monthly_notes_folder: String,
+    pub monthly_note_template: String,
+    /// Format of yearly notes
+    pub yearlynote: String,
+    pub yearly_notes_folder: String,
+    pub yearly_note_template: String,
     pub heading_completions: bool,
     pub title_headings: bool,
     pub unresolved_diagnostics: bool,
+    pub ambiguous_diagnostics: bool,
     pub semantic_tokens: bool,
+    /// Send a semantic token type for each kind of token instead of standard types told apart by modifiers
+    pub custom_semantic_token_types: bool,
     pub tags_in_codeblocks: bool,
     pub references_in_codeblocks: bool,
     pub include_md_extension_md_link: bool,
@@ -27,6 +50,18 @@ pub struct Settings {
     pub inlay_hints: bool,
     pub block_transclusion: bool,
     pub block_transclusion_length: EmbeddedBlockTransclusionLength,
+    /// Weights of the frecency of notes in the ranking of link completions; 0 to ignore
+    pub frecency_recency_weight: u32,
+    pub frecency_opened_weight: u32,
+    pub frecency_backlinks_weight: u32,
+    /// The path of the file in links that the server inserts
+    pub new_link_format: NewLinkFormat,
+    /// Insert markdown links, `[display](path.md)`, instead of wikilinks where the link syntax is not given
+    pub use_markdown_links: bool,
+    /// How the ids of blocks that are linked to for the first time are made
+    pub block_id_strategy: BlockIdStrategy,
+    /// Length of random block ids
+    pub block_id_length: usize,
 }
 
 #[derive(Clone, Debug, Deserialize)]
@@ -36,6 +71,27 @@ pub enum Case {
     Respect,
 }
 
+/// Obsidian's "New link format"
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+pub enum NewLinkFormat {
+    /// The shortest path that links to the file unambiguously: `meeting`, or `project-a/meeting` if there are several
+    Shortest,
+    /// Relative to the linking file: `./meeting` or `../project-a/meeting`
+    Relative,
+    /// Relative to the vault root: `projects/project-a/meeting`
+    Absolute,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+pub enum BlockIdStrategy {
+    /// Random letters and digits: `^x8d2k`
+    Random,
+    /// The first words of the block text: `^decision-rollout`
+    Slug,
+    /// The next number in the file: `^1`, `^2`
+    Sequential,
+}
+
 #[derive(Clone, Debug, Deserialize)]
 pub enum EmbeddedBlockTransclusionLength {
     Partial(usize),
@@ -44,11 +100,41 @@ pub enum EmbeddedBlockTransclusionLength {
 
 impl Settings {
     pub fn new(root_dir: &Path, capabilities: &ClientCapabilities) -> anyhow::Result<Settings> {
-        let obsidian_daily_note_config = obsidian_daily_note_config(root_dir).unwrap_or_default();
-        let obsidian_new_file_folder_path = obsidian_new_file_folder_path(root_dir);
         let expanded = shellexpand::tilde("~/.config/moxide/settings");
+
+        Settings::from_sources(Some(&expanded), root_dir, capabilities)
+    }
+
+    /// The settings of the vault at the root alone, without the user's settings file, so that tests do not depend on it
+    #[cfg(test)]
+    pub fn for_vault(root_dir: &Path) -> Settings {
+        Settings::from_sources(None, root_dir, &ClientCapabilities::default()).unwrap()
+    }
+
+    fn from_sources(
+        user_settings: Option<&str>,
+        root_dir: &Path,
+        capabilities: &ClientCapabilities,
+    ) -> anyhow::Result<Settings> {
+        let obsidian_periodic_notes_config =
+            obsidian_periodic_notes_config(root_dir).unwrap_or_default();
+        let obsidian_daily_note_config = obsidian_daily_note_config(root_dir)
+            .or(obsidian_periodic_notes_config.daily)
+            .unwrap_or_default();
+        let obsidian_weekly_note_config = obsidian_periodic_notes_config.weekly.unwrap_or_default();
+        let obsidian_monthly_note_config =
+            obsidian_periodic_notes_config.monthly.unwrap_or_default();
+        let obsidian_yearly_note_config = obsidian_periodic_notes_config.yearly.unwrap_or_default();
+        let obsidian_new_file_folder_path = obsidian_new_file_folder_path(root_dir);
+        let obsidian_link_config = obsidian_link_config(root_dir).unwrap_or_default();
+        let obsidian_unique_note_config = obsidian_unique_note_config(root_dir).unwrap_or_default();
         let settings = Config::builder()
-            .add_source(File::with_name(&expanded).required(false))
+            .add_source(
+                user_settings
+                    .map(|user_settings| File::with_name(user_settings).required(false))
+                    .into_iter()
+                    .collect::<Vec<_>>(),
+            )
             .add_source(
                 File::with_name(&format!(
                     "{}/.moxide",
@@ -60,22 +146,105 @@ impl Settings {
             )
             .set_default(
                 "new_file_folder_path",
-                obsidian_new_file_folder_path.unwrap_or("".to_string()),
+                obsidian_new_file_folder_path
+                    .clone()
+                    .unwrap_or("".to_string()),
             )?
             .set_default(
                 "daily_notes_folder",
                 obsidian_daily_note_config.folder.unwrap_or("".to_string()),
             )?
+            .set_default(
+                "daily_note_template",
+                obsidian_daily_note_config
+                    .template
+                    .unwrap_or("".to_string()),
+            )?
+            .set_default(
+                "templates_folder",
+                obsidian_templates_folder(root_dir).unwrap_or("templates".to_string()),
+            )?
             .set_default(
                 "dailynote",
                 obsidian_daily_note_config
                     .format
                     .unwrap_or("%Y-%m-%d".to_string()),
             )?
+            .set_default(
+                "unique_note_prefix",
+                obsidian_unique_note_config
+                    .format
+                    .unwrap_or("%Y%m%d%H%M".to_string()),
+            )?
+            .set_default(
+                "unique_notes_folder",
+                obsidian_unique_note_config
+                    .folder
+                    .or(obsidian_new_file_folder_path)
+                    .unwrap_or("".to_string()),
+            )?
+            .set_default(
+                "unique_note_template",
+                obsidian_unique_note_config
+                    .template
+                    .unwrap_or("".to_string()),
+            )?
+            .set_default(
+                "weeklynote",
+                obsidian_weekly_note_config
+                    .format
+                    .unwrap_or("%G-W%V".to_string()),
+            )?
+            .set_default(
+                "weekly_notes_folder",
+                obsidian_weekly_note_config.folder.unwrap_or("".to_string()),
+            )?
+            .set_default(
+                "weekly_note_template",
+                obsidian_weekly_note_config
+                    .template
+                    .unwrap_or("".to_string()),
+            )?
+            .set_default(
+                "monthlynote",
+                obsidian_monthly_note_config
+                    .format
+                    .unwrap_or("%Y-%m".to_string()),
+            )?
+            .set_default(
+                "monthly_notes_folder",
+                obsidian_monthly_note_config
+                    .folder
+                    .unwrap_or("".to_string()),
+            )?
+            .set_default(
+                "monthly_note_template",
+                obsidian_monthly_note_config
+                    .template
+                    .unwrap_or("".to_string()),
+            )?
+            .set_default(
+                "yearlynote",
+                obsidian_yearly_note_config
+                    .format
+                    .unwrap_or("%Y".to_string()),
+            )?
+            .set_default(
+                "yearly_notes_folder",
+                obsidian_yearly_note_config.folder.unwrap_or("".to_string()),
+            )?
+            .set_default(
+                "yearly_note_template",
+                obsidian_yearly_note_config
+                    .template
+                    .unwrap_or("".to_string()),
+            )?
             .set_default("heading_completions", true)?
             .set_default("unresolved_diagnostics", true)?
+            .set_default("ambiguous_diagnostics", true)?
             .set_default("title_headings", true)?
             .set_default("semantic_tokens", true)?
+            .set_default("custom_semantic_token_types", false)?
             .set_default("tags_in_codeblocks", true)?
             .set_default("references_in_codeblocks", true)?
             .set_default("include_md_extension_md_link", false)?
@@ -85,6 +254,21 @@ impl Settings {
             .set_default("inlay_hints", true)?
             .set_default("block_transclusion", true)?
             .set_default("block_transclusion_length", "Full")?
+            .set_default("frecency_recency_weight", 32)?
+            .set_default("frecency_opened_weight", 32)?
+            .set_default("frecency_backlinks_weight", 8)?
+            .set_default(
+                "new_link_format",
+                obsidian_link_config
+                    .new_link_format
+                    .unwrap_or("Shortest".to_string()),
+            )?
+            .set_default(
+                "use_markdown_links",
+                obsidian_link_config.use_markdown_links.unwrap_or(false),
+            )?
+            .set_default("block_id_strategy", "Random")?
+            .set_default("block_id_length", 5)?
             .set_override_option(
                 "semantic_tokens",
                 capabilities.text_document.as_ref().and_then(|it| {
@@ -107,6 +291,7 @@ impl Settings {
 struct ObsidianDailyNoteConfig {
     folder: Option<String>,
     format: Option<String>,
+    template: Option<String>,
 }
 
 fn obsidian_daily_note_config(root_dir: &Path) -> Option<ObsidianDailyNoteConfig> {
@@ -117,9 +302,77 @@ fn obsidian_daily_note_config(root_dir: &Path) -> Option<ObsidianDailyNoteConfig
     Some(ObsidianDailyNoteConfig {
         folder: config.folder,
         format: config.format.map(|x| convert_momentjs_to_chrono_format(&x)),
+        template: config.template,
     })
 }
 
+/// The Periodic Notes plugin has the same settings as the Daily Notes plugin for every period
+#[derive(Deserialize, Debug, Default)]
+struct ObsidianPeriodicNotesConfig {
+    daily: Option<ObsidianDailyNoteConfig>,
+    weekly: Option<ObsidianDailyNoteConfig>,
+    monthly: Option<ObsidianDailyNoteConfig>,
+    yearly: Option<ObsidianDailyNoteConfig>,
+}
+
+fn obsidian_periodic_notes_config(root_dir: &Path) -> Option<ObsidianPeriodicNotesConfig> {
+    let periodic_notes_config_file = root_dir
+        .join(".obsidian")
+        .join("plugins")
+        .join("periodic-notes")
+        .join("data.json");
+    let file = std::fs::read_to_string(periodic_notes_config_file).ok()?;
+    let config: ObsidianPeriodicNotesConfig = serde_json::from_str(&file).ok()?;
+
+    let convert = |config: Option<ObsidianDailyNoteConfig>| {
+        config.map(|config| ObsidianDailyNoteConfig {
+            folder: config.folder,
+            // unset formats are saved as empty strings
+            format: config
+                .format
+                .filter(|x| !x.is_empty())
+                .map(|x| convert_momentjs_to_chrono_format(&x)),
+            template: config.template,
+        })
+    };
+
+    Some(ObsidianPeriodicNotesConfig {
+        daily: convert(config.daily),
+        weekly: convert(config.weekly),
+        monthly: convert(config.monthly),
+        yearly: convert(config.yearly),
+    })
+}
+
+/// The Unique Note Creator plugin has the same settings as the Daily Notes plugin
+fn obsidian_unique_note_config(root_dir: &Path) -> Option<ObsidianDailyNoteConfig> {
+    let unique_note_config_file = root_dir.join(".obsidian").join("zk-prefixer.json");
+    let file = std::fs::read_to_string(unique_note_config_file).ok()?;
+    let config: ObsidianDailyNoteConfig = serde_json::from_str(&file).ok()?;
+
+    Some(ObsidianDailyNoteConfig {
+        folder: config.folder.filter(|folder| !folder.is_empty()),
+        format: config
+            .format
+            .filter(|x| !x.is_empty())
+            .map(|x| convert_momentjs_to_chrono_format(&x)),
+        template: config.template,
+    })
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct ObsidianTemplatesConfig {
+    folder: Option<String>,
+}
+
+fn obsidian_templates_folder(root_dir: &Path) -> Option<String> {
+    let templates_config_file = root_dir.join(".obsidian").join("templates.json");
+    let file = std::fs::read_to_string(templates_config_file).ok()?;
+    let config: ObsidianTemplatesConfig = serde_json::from_str(&file).ok()?;
+
+    config.folder
+}
+
 fn obsidian_new_file_folder_path(root_dir: &Path) -> Option<String> {
     let obsidian_settings_file = root_dir.join(".obsidian").join("app.json");
     let file = std::fs::read(obsidian_settings_file).ok();
@@ -134,25 +387,55 @@ fn obsidian_new_file_folder_path(root_dir: &Path) -> Option<String> {
             .and_then(|value| value.as_str())
             .map(String::from);
 
-        if config.get("newFileLocation").and_then(|v| v.as_str()) == Some(&"folder") {
-            return path;
+        if config.get("newFileLocation").and_then(|v| v.as_str()) == Some("folder") {
+            path
         } else {
-            return None;
+            None
         }
     });
 
     new_file_folder_path
 }
 
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+struct ObsidianLinkConfig {
+    new_link_format: Option<String>,
+    use_markdown_links: Option<bool>,
+}
+
+/// The link settings of Obsidian's "Files and links" options
+fn obsidian_link_config(root_dir: &Path) -> Option<ObsidianLinkConfig> {
+    let obsidian_settings_file = root_dir.join(".obsidian").join("app.json");
+    let file = std::fs::read_to_string(obsidian_settings_file).ok()?;
+    let config: ObsidianLinkConfig = serde_json::from_str(&file).ok()?;
+
+    Some(ObsidianLinkConfig {
+        // Obsidian writes the format in lowercase: shortest, relative or absolute
+        new_link_format: config.new_link_format.and_then(|format| {
+            match format.as_str() {
+                "shortest" => Some("Shortest"),
+                "relative" => Some("Relative"),
+                "absolute" => Some("Absolute"),
+                _ => None,
+            }
+            .map(String::from)
+        }),
+        use_markdown_links: config.use_markdown_links,
+    })
+}
+
 use std::collections::HashMap;
 
-// GPT-4 code
+/// Longer tokens come before the shorter tokens that they start with, so that the longest token is matched
 fn momentjs_to_chrono_format_map() -> IndexMap<&'static str, &'static str> {
     let mut map = IndexMap::new();
 
     // Year
     map.insert("YYYY", "%Y");
     map.insert("YY", "%y");
+    map.insert("GGGG", "%G");
+    map.insert("GG", "%g");
 
     // Month
     map.insert("MMMM", "%B");
@@ -160,24 +443,73 @@ fn momentjs_to_chrono_format_map() -> IndexMap<&'static str, &'static str> {
     map.insert("MM", "%m");
     map.insert("M", "%-m");
 
+    // Week of year
+    map.insert("WW", "%V");
+    map.insert("W", "%-V");
+    map.insert("ww", "%U");
+    map.insert("w", "%-U");
+
     // Day
+    map.insert("DDDD", "%j");
+    map.insert("DDD", "%-j");
     map.insert("DD", "%d");
     map.insert("D", "%-d");
 
     // Weekday
     map.insert("dddd", "%A");
     map.insert("ddd", "%a");
+    map.insert("d", "%w");
+    map.insert("E", "%u");
+
+    // Time
+    map.insert("HH", "%H");
+    map.insert("H", "%-H");
+    map.insert("hh", "%I");
+    map.insert("h", "%-I");
+    map.insert("mm", "%M");
+    map.insert("m", "%-M");
+    map.insert("ss", "%S");
+    map.insert("s", "%-S");
+    map.insert("A", "%p");
+    map.insert("a", "%P");
+    map.insert("X", "%s");
+    map.insert("ZZ", "%z");
+    map.insert("Z", "%:z");
 
     map
 }
 
-// GPT-4 code
-fn convert_momentjs_to_chrono_format(moment_format: &str) -> String {
+/// Tokenize the moment format, matching the longest token at each position. Text in `[brackets]` is literal, as in moment
+pub fn convert_momentjs_to_chrono_format(moment_format: &str) -> String {
     let format_map = momentjs_to_chrono_format_map();
-    let mut chrono_format = moment_format.to_string();
+    let mut chrono_format = String::new();
+    let mut rest = moment_format;
 
-    for (moment_token, chrono_token) in format_map.iter() {
-        chrono_format = chrono_format.replace(moment_token, chrono_token);
+    while let Some(c) = rest.chars().next() {
+        if c == '[' {
+            if let Some(end) = rest.find(']') {
+                chrono_format.push_str(&rest[1..end].replace('%', "%%"));
+                rest = &rest[end + 1..];
+                continue;
+            }
+        }
+
+        match format_map
+            .iter()
+            .find(|(moment_token, _)| rest.starts_with(*moment_token))
+        {
+            Some((moment_token, chrono_token)) => {
+                chrono_format.push_str(chrono_token);
+                rest = &rest[moment_token.len()..];
+            }
+            None => {
+                match c {
+                    '%' => chrono_format.push_str("%%"),
+                    c => chrono_format.push(c),
+                }
+                rest = &rest[c.len_utf8()..];
+            }
+        }
     }
 
     chrono_format
@@ -189,8 +521,8 @@ mod test {
     use std::path::PathBuf;
 
     use crate::config::{
-        convert_momentjs_to_chrono_format, obsidian_daily_note_config,
-        obsidian_new_file_folder_path,
+        convert_momentjs_to_chrono_format, obsidian_daily_note_config, obsidian_link_config,
+        obsidian_new_file_folder_path, obsidian_periodic_notes_config, obsidian_templates_folder,
     };
 
     #[test]
@@ -200,6 +532,18 @@ mod test {
         assert_eq!(chrono_format, "%Y-%m-%d");
     }
 
+    #[test]
+    fn test_format_conversion_tokens() {
+        assert_eq!(
+            convert_momentjs_to_chrono_format("GGGG-[W]WW dddd HH:mm:ss"),
+            "%G-W%V %A %H:%M:%S"
+        );
+        assert_eq!(
+            convert_momentjs_to_chrono_format("[Daily] D MMM YY"),
+            "Daily %-d %b %y"
+        );
+    }
+
     #[test]
     fn test_daily_note_config() {
         let daily_notes_config = obsidian_daily_note_config(&root_dir()).unwrap();
@@ -208,6 +552,10 @@ mod test {
             daily_notes_config.folder,
             Some("the-daily-notes-folder".to_string())
         );
+        assert_eq!(
+            daily_notes_config.template,
+            Some("templates/daily".to_string())
+        );
     }
 
     #[test]
@@ -219,6 +567,33 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_periodic_notes_config() {
+        let periodic_notes_config = obsidian_periodic_notes_config(&root_dir()).unwrap();
+        let weekly = periodic_notes_config.weekly.unwrap();
+        assert_eq!(weekly.format, Some("%G-W%V".to_string()));
+        assert_eq!(weekly.folder, Some("reviews/weekly".to_string()));
+        assert_eq!(weekly.template, Some("templates/weekly".to_string()));
+        assert_eq!(
+            periodic_notes_config.monthly.unwrap().format,
+            Some("%Y-%m".to_string())
+        );
+        assert!(periodic_notes_config.yearly.is_none());
+    }
+
+    #[test]
+    fn test_link_config() {
+        let link_config = obsidian_link_config(&root_dir()).unwrap();
+        assert_eq!(link_config.new_link_format, Some("Shortest".to_string()));
+        assert_eq!(link_config.use_markdown_links, Some(false));
+    }
+
+    #[test]
+    fn test_templates_folder() {
+        let templates_folder = obsidian_templates_folder(&root_dir());
+        assert_eq!(templates_folder, Some("templates".to_string()));
+    }
+
     fn root_dir() -> PathBuf {
         PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("TestFiles")
     }
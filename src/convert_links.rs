@@ -0,0 +1,259 @@
+use std::{collections::HashMap, path::Path};
+
+use serde::{Deserialize, Serialize};
+use tower_lsp::lsp_types::{TextEdit, Url, WorkspaceEdit};
+
+use crate::{
+    config::Settings,
+    link_format::{
+        file_name, folder_ref_path, link_path, markdown_link, relative_link_path, wiki_link,
+        FileNames,
+    },
+    vault::{Reference, Vault},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LinkStyle {
+    /// `[[path#infile|display]]`
+    Wiki,
+    /// `[display](path.md#infile)`
+    Markdown,
+}
+
+/// Arguments of the `convertLinks` command
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConvertLinks {
+    /// The style to convert the links to
+    pub to: LinkStyle,
+    /// Only convert the links in this file; all the links in the vault if not given
+    pub uri: Option<Url>,
+}
+
+/// The style of a link; None for a reference that is not a link
+pub fn link_style(reference: &Reference) -> Option<LinkStyle> {
+    match reference {
+        Reference::WikiFileLink(..)
+        | Reference::WikiHeadingLink(..)
+        | Reference::WikiIndexedBlockLink(..) => Some(LinkStyle::Wiki),
+        Reference::MDFileLink(..)
+        | Reference::MDHeadingLink(..)
+        | Reference::MDIndexedBlockLink(..) => Some(LinkStyle::Markdown),
+        Reference::Tag(..) | Reference::Footnote(..) | Reference::LinkRef(..) => None,
+    }
+}
+
+/// The link rewritten in the other style, or None for a reference that is not a link or that is already in the style.
+/// Wikilinks become markdown links with a path relative to the linking file, so that they also work outside of the vault;
+/// markdown links become wikilinks with the path of the link format of the settings. Links that do not match exactly
+/// one file keep their path
+pub fn convert_link(
+    vault: &Vault,
+    settings: &Settings,
+    file_names: &FileNames,
+    path: &Path,
+    reference: &Reference,
+    to: LinkStyle,
+) -> Option<String> {
+    let (style, link_path_text, infile_ref) = match reference {
+        Reference::WikiFileLink(data) => (LinkStyle::Wiki, data.reference_text.as_str(), None),
+        Reference::MDFileLink(data) => (LinkStyle::Markdown, data.reference_text.as_str(), None),
+        Reference::WikiHeadingLink(_, file, heading) => {
+            (LinkStyle::Wiki, file.as_str(), Some(heading.to_string()))
+        }
+        Reference::MDHeadingLink(_, file, heading) => (
+            LinkStyle::Markdown,
+            file.as_str(),
+            Some(heading.replace("%20", " ")),
+        ),
+        Reference::WikiIndexedBlockLink(_, file, index) => {
+            (LinkStyle::Wiki, file.as_str(), Some(format!("^{}", index)))
+        }
+        Reference::MDIndexedBlockLink(_, file, index) => (
+            LinkStyle::Markdown,
+            file.as_str(),
+            Some(format!("^{}", index)),
+        ),
+        Reference::Tag(..) | Reference::Footnote(..) | Reference::LinkRef(..) => return None,
+    };
+
+    if style == to {
+        return None;
+    }
+
+    let root_dir = vault.root_dir();
+    let link_path_text = link_path_text.replace("%20", " ");
+    let from_dir = folder_ref_path(root_dir, path)?;
+
+    let target = match file_names.matching_paths(&link_path_text, &from_dir)[..] {
+        [target] => Some(root_dir.join(format!("{}.md", target))),
+        _ => None,
+    };
+    let file_name = target
+        .as_deref()
+        .and_then(|target| target.file_stem()?.to_str())
+        .unwrap_or_else(|| file_name(&link_path_text))
+        .to_string();
+
+    let display = reference
+        .data()
+        .display_text
+        .as_deref()
+        .map(str::trim)
+        .filter(|display| !display.is_empty());
+    // the display text that a link without display text shows
+    let default_display = match infile_ref.as_deref() {
+        Some(heading) if !heading.starts_with('^') => heading,
+        _ => &file_name,
+    };
+
+    let new_link = match to {
+        LinkStyle::Markdown => {
+            let new_path = match &target {
+                Some(target) => relative_link_path(path, target)?,
+                None => link_path_text,
+            };
+
+            markdown_link(
+                settings,
+                &new_path,
+                infile_ref.as_deref(),
+                Some(display.unwrap_or(default_display)),
+            )
+        }
+        LinkStyle::Wiki => {
+            let new_path = match &target {
+                Some(target) => link_path(settings, file_names, root_dir, path, target)?,
+                None => link_path_text,
+            };
+
+            wiki_link(
+                settings,
+                &new_path,
+                infile_ref.as_deref(),
+                display.filter(|display| !display.eq_ignore_ascii_case(default_display)),
+            )
+        }
+    };
+
+    Some(new_link)
+}
+
+/// The edits that convert the links of the files to the style; files without links to convert have no edits
+pub fn convert_links_edit<'a>(
+    vault: &Vault,
+    settings: &Settings,
+    paths: impl IntoIterator<Item = &'a Path>,
+    to: LinkStyle,
+) -> WorkspaceEdit {
+    let file_names = vault.select_file_names();
+
+    let changes = paths
+        .into_iter()
+        .flat_map(|path| {
+            let edits = vault
+                .select_references(Some(path))?
+                .into_iter()
+                .flat_map(|(_, reference)| {
+                    Some(TextEdit {
+                        range: *reference.data().range,
+                        new_text: convert_link(vault, settings, file_names, path, reference, to)?,
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            match edits.is_empty() {
+                true => None,
+                false => Some((Url::from_file_path(path).ok()?, edits)),
+            }
+        })
+        .collect::<HashMap<_, _>>();
+
+    WorkspaceEdit {
+        changes: Some(changes),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::{
+        config::Settings,
+        vault::{Reference, Vault},
+    };
+
+    use super::{convert_link, LinkStyle};
+
+    #[test]
+    fn converts_links() {
+        let root_dir = Path::new("/vault");
+        let mut settings = Settings::for_vault(root_dir);
+        settings.include_md_extension_md_link = true;
+        settings.include_md_extension_wikilink = false;
+        let vault = Vault::from_texts(
+            &settings,
+            root_dir,
+            &[
+                ("Test.md", ""),
+                ("Resolved File.md", ""),
+                ("folder/Nested.md", ""),
+            ],
+        );
+        let file_names = vault.select_file_names();
+        let path = root_dir.join("Test.md");
+        let nested_path = root_dir.join("folder/Nested.md");
+
+        let convert_from = |path: &Path, link: &str, to: LinkStyle| {
+            let reference = Reference::new(link, "Test").next()?;
+            convert_link(&vault, &settings, file_names, path, &reference, to)
+        };
+        let convert = |link: &str, to: LinkStyle| convert_from(&path, link, to);
+
+        assert_eq!(
+            convert("[[Resolved File]]", LinkStyle::Markdown).as_deref(),
+            Some("[Resolved File](Resolved%20File.md)")
+        );
+        assert_eq!(
+            convert(
+                "[[Unresolved Note#Some Heading|shown]]",
+                LinkStyle::Markdown
+            )
+            .as_deref(),
+            Some("[shown](Unresolved%20Note.md#Some%20Heading)")
+        );
+        assert_eq!(
+            convert("[[Unresolved Note#^block]]", LinkStyle::Markdown).as_deref(),
+            Some("[Unresolved Note](Unresolved%20Note.md#^block)")
+        );
+        assert_eq!(
+            convert("[Resolved File](Resolved%20File.md)", LinkStyle::Wiki).as_deref(),
+            Some("[[Resolved File]]")
+        );
+        assert_eq!(
+            convert(
+                "[shown](Unresolved%20Note.md#Some%20Heading)",
+                LinkStyle::Wiki
+            )
+            .as_deref(),
+            Some("[[Unresolved Note#Some Heading|shown]]")
+        );
+        assert_eq!(convert("[[Resolved File]]", LinkStyle::Wiki), None);
+
+        assert_eq!(
+            convert_from(&nested_path, "[[Resolved File]]", LinkStyle::Markdown).as_deref(),
+            Some("[Resolved File](../Resolved%20File.md)")
+        );
+        assert_eq!(
+            convert_from(
+                &nested_path,
+                "[file](../Resolved%20File.md)",
+                LinkStyle::Wiki
+            )
+            .as_deref(),
+            Some("[[Resolved File|file]]")
+        );
+    }
+}
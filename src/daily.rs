@@ -1,7 +1,262 @@
-use crate::config::Settings;
+use std::path::{Path, PathBuf};
 
-pub fn filename_is_formatted(context: &Settings, filename: &str) -> bool {
-    let try_parsed = chrono::NaiveDate::parse_from_str(&filename, &context.dailynote);
+use chrono::{Days, NaiveDate, NaiveDateTime};
+use itertools::Itertools;
+use pathdiff::diff_paths;
+use serde::{Deserialize, Serialize};
+use tower_lsp::lsp_types::Url;
 
-    try_parsed.is_ok()
+use crate::{
+    config::Settings,
+    periodic::{note_path, parse_note_path, Period},
+    vault::{TaskStatus, Vault},
+};
+
+/// The file of the daily note for the date; the file may not exist yet
+pub fn daily_note_path(settings: &Settings, root_dir: &Path, date: NaiveDate) -> PathBuf {
+    note_path(settings, root_dir, Period::Day, date)
+}
+
+pub fn formatted_note_path(format: &str, folder: &Path, datetime: NaiveDateTime) -> PathBuf {
+    folder.join(format!("{}.md", datetime.format(format)))
+}
+
+/// The date of a daily note from its path, in the `dailynote` format
+pub fn daily_note_date(settings: &Settings, root_dir: &Path, path: &Path) -> Option<NaiveDate> {
+    match parse_note_path(settings, root_dir, path)? {
+        (Period::Day, date) => Some(date),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Previous,
+    Next,
+}
+
+/// The daily note before or after the daily note at the path: the nearest one in the vault, skipping days without a note,
+/// or, if `create` is set, the note of the adjacent day, which may not exist yet
+pub fn adjacent_daily_note(
+    vault: &Vault,
+    settings: &Settings,
+    path: &Path,
+    direction: Direction,
+    create: bool,
+) -> Option<(PathBuf, NaiveDate)> {
+    let date = daily_note_date(settings, vault.root_dir(), path)?;
+
+    let mut notes = vault.md_files.keys().flat_map(|note_path| {
+        Some((
+            note_path.clone(),
+            daily_note_date(settings, vault.root_dir(), note_path)?,
+        ))
+    });
+
+    if create {
+        let adjacent = match direction {
+            Direction::Previous => date.checked_sub_days(Days::new(1))?,
+            Direction::Next => date.checked_add_days(Days::new(1))?,
+        };
+
+        // the note of the day may be in another folder than new notes are created in
+        return notes
+            .find(|(_, note_date)| *note_date == adjacent)
+            .or_else(|| {
+                Some((
+                    daily_note_path(settings, vault.root_dir(), adjacent),
+                    adjacent,
+                ))
+            });
+    }
+
+    match direction {
+        Direction::Previous => notes
+            .filter(|(_, note_date)| *note_date < date)
+            .max_by_key(|(_, note_date)| *note_date),
+        Direction::Next => notes
+            .filter(|(_, note_date)| *note_date > date)
+            .min_by_key(|(_, note_date)| *note_date),
+    }
+}
+
+/// Arguments of the `dailyNotes` command; the range is inclusive and either end may be open
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct DailyNotesQuery {
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct DailyNoteSummary {
+    pub date: NaiveDate,
+    /// relative to the vault root
+    pub path: String,
+    pub uri: Url,
+    pub word_count: usize,
+    pub open_tasks: usize,
+    pub done_tasks: usize,
+    /// without the '#', each once
+    pub tags: Vec<String>,
+}
+
+/// The existing daily notes in the range, ordered by date
+pub fn daily_notes(
+    vault: &Vault,
+    settings: &Settings,
+    query: &DailyNotesQuery,
+) -> Vec<DailyNoteSummary> {
+    vault
+        .md_files
+        .iter()
+        .flat_map(|(path, md_file)| {
+            let date = daily_note_date(settings, vault.root_dir(), path)?;
+
+            let after_from = match query.from {
+                Some(from) => date >= from,
+                None => true,
+            };
+            let before_to = match query.to {
+                Some(to) => date <= to,
+                None => true,
+            };
+            if !(after_from && before_to) {
+                return None;
+            }
+
+            let word_count = vault
+                .ropes
+                .get(path)
+                .map(|rope| rope.chars().collect::<String>().split_whitespace().count())
+                .unwrap_or_default();
+
+            Some(DailyNoteSummary {
+                date,
+                path: diff_paths(path, vault.root_dir())?.to_str()?.to_string(),
+                uri: Url::from_file_path(path).ok()?,
+                word_count,
+                open_tasks: md_file
+                    .tasks
+                    .iter()
+                    .filter(|task| task.status.is_open())
+                    .count(),
+                done_tasks: md_file
+                    .tasks
+                    .iter()
+                    .filter(|task| task.status == TaskStatus::Done)
+                    .count(),
+                tags: md_file
+                    .tags
+                    .iter()
+                    .map(|tag| tag.tag_ref.clone())
+                    .unique()
+                    .collect(),
+            })
+        })
+        .sorted_by_key(|summary| summary.date)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use chrono::NaiveDate;
+
+    use crate::{config::Settings, vault::Vault};
+
+    use super::{adjacent_daily_note, daily_notes, DailyNotesQuery, Direction};
+
+    #[test]
+    fn adjacent_daily_notes() {
+        let root_dir = Path::new("/vault");
+        let mut settings = Settings::for_vault(root_dir);
+        settings.daily_notes_folder = "daily".to_string();
+        let vault = Vault::from_texts(
+            &settings,
+            root_dir,
+            &[
+                ("2024-03-23.md", ""),
+                ("2024-03-24.md", ""),
+                ("2024-03-28.md", ""),
+                ("2024-03-30.md", ""),
+                ("Test.md", ""),
+            ],
+        );
+        let date = |d| NaiveDate::from_ymd_opt(2024, 3, d).unwrap();
+
+        let adjacent = |day: &str, direction, create| {
+            adjacent_daily_note(
+                &vault,
+                &settings,
+                &root_dir.join(format!("{}.md", day)),
+                direction,
+                create,
+            )
+        };
+
+        assert_eq!(
+            adjacent("2024-03-24", Direction::Previous, false),
+            Some((root_dir.join("2024-03-23.md"), date(23)))
+        );
+        // there is no note for the 29th
+        assert_eq!(
+            adjacent("2024-03-28", Direction::Next, false),
+            Some((root_dir.join("2024-03-30.md"), date(30)))
+        );
+        assert_eq!(
+            adjacent("2024-03-28", Direction::Next, true),
+            Some((root_dir.join("daily/2024-03-29.md"), date(29)))
+        );
+        // the note of the 23rd is outside of the daily notes folder
+        assert_eq!(
+            adjacent("2024-03-22", Direction::Next, true),
+            Some((root_dir.join("2024-03-23.md"), date(23)))
+        );
+        assert_eq!(adjacent("Test", Direction::Next, false), None);
+    }
+
+    #[test]
+    fn daily_notes_in_range() {
+        let root_dir = Path::new("/vault");
+        let settings = Settings::for_vault(root_dir);
+        let vault = Vault::from_texts(
+            &settings,
+            root_dir,
+            &[
+                ("2024-03-20.md", ""),
+                (
+                    "daily/2024-03-21.md",
+                    "- [ ] Open task #work\n- [x] Done task #work #home\n",
+                ),
+                ("2024-03-22.md", ""),
+                ("2024-03-23.md", ""),
+                ("2024-03-24.md", ""),
+                ("Test.md", ""),
+            ],
+        );
+
+        let query = DailyNotesQuery {
+            from: NaiveDate::from_ymd_opt(2024, 3, 21),
+            to: NaiveDate::from_ymd_opt(2024, 3, 23),
+        };
+
+        let summaries = daily_notes(&vault, &settings, &query);
+
+        assert_eq!(
+            summaries
+                .iter()
+                .map(|summary| summary.path.as_str())
+                .collect::<Vec<_>>(),
+            vec!["daily/2024-03-21.md", "2024-03-22.md", "2024-03-23.md"]
+        );
+
+        let summary = &summaries[0];
+        assert_eq!(summary.date, NaiveDate::from_ymd_opt(2024, 3, 21).unwrap());
+        assert_eq!(summary.word_count, 12);
+        assert_eq!((summary.open_tasks, summary.done_tasks), (1, 1));
+        assert_eq!(summary.tags, vec!["work", "home"]);
+    }
 }
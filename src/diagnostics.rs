@@ -1,10 +1,12 @@
 use std::path::{Path, PathBuf};
 
+use itertools::Itertools;
 use rayon::prelude::*;
 use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, Url};
 
 use crate::{
     config::Settings,
+    link_format::{folder_ref_path, FileNames},
     vault::{self, Reference, Referenceable, Vault},
 };
 
@@ -36,13 +38,69 @@ pub fn path_unresolved_references<'a>(
     Some(unresolved)
 }
 
+/// The file part of a link to a file, heading or block
+pub fn link_file_path(reference: &Reference) -> Option<&str> {
+    match reference {
+        Reference::WikiFileLink(data) | Reference::MDFileLink(data) => Some(&data.reference_text),
+        Reference::WikiHeadingLink(_, file, _)
+        | Reference::WikiIndexedBlockLink(_, file, _)
+        | Reference::MDHeadingLink(_, file, _)
+        | Reference::MDIndexedBlockLink(_, file, _) => Some(file),
+        Reference::Tag(..) | Reference::Footnote(..) | Reference::LinkRef(..) => None,
+    }
+}
+
+/// Links in the file that match more than one file, as `[[meeting]]` does with `project-a/meeting.md` and `project-b/meeting.md`,
+/// with the paths of the files that they match
+pub fn path_ambiguous_references<'a, 'b>(
+    vault: &'a Vault,
+    path: &'a Path,
+    file_names: &'b FileNames,
+) -> Option<Vec<(&'a Reference, Vec<&'b str>)>> {
+    let references = vault.select_references(Some(path))?;
+    let from_dir = folder_ref_path(vault.root_dir(), path)?;
+
+    let ambiguous = references
+        .into_iter()
+        .flat_map(|(_, reference)| {
+            let matching_paths = file_names.matching_paths(link_file_path(reference)?, &from_dir);
+
+            (matching_paths.len() > 1).then_some((reference, matching_paths))
+        })
+        .collect();
+
+    Some(ambiguous)
+}
+
 pub fn diagnostics(
     vault: &Vault,
     settings: &Settings,
     (path, _uri): (&PathBuf, &Url),
 ) -> Option<Vec<Diagnostic>> {
+    let file_names = settings
+        .ambiguous_diagnostics
+        .then(|| vault.select_file_names());
+    let ambiguous = file_names
+        .as_ref()
+        .and_then(|file_names| path_ambiguous_references(vault, path, file_names))
+        .into_iter()
+        .flatten()
+        .map(|(reference, matching_paths)| Diagnostic {
+            range: *reference.data().range,
+            message: format!(
+                "Ambiguous Reference: matches {}",
+                matching_paths
+                    .iter()
+                    .map(|path| format!("{}.md", path))
+                    .join(", ")
+            ),
+            source: Some("Obsidian LS".into()),
+            severity: Some(DiagnosticSeverity::WARNING),
+            ..Default::default()
+        });
+
     if !settings.unresolved_diagnostics {
-        return None;
+        return Some(ambiguous.collect());
     }
 
     let unresolved = path_unresolved_references(vault, path)?;
@@ -72,5 +130,5 @@ pub fn diagnostics(
         })
         .collect();
 
-    Some(diags)
+    Some(ambiguous.chain(diags).collect())
 }
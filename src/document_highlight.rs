@@ -0,0 +1,123 @@
+use std::path::Path;
+
+use itertools::Itertools;
+use tower_lsp::lsp_types::{DocumentHighlight, DocumentHighlightKind, Position};
+
+use crate::vault::{Referenceable, Vault};
+
+pub fn document_highlight(
+    vault: &Vault,
+    cursor_position: Position,
+    path: &Path,
+) -> Option<Vec<DocumentHighlight>> {
+    let referenceables = match (
+        vault.select_referenceable_at_position(path, cursor_position),
+        vault.select_reference_at_position(path, cursor_position),
+    ) {
+        (Some(referenceable @ Referenceable::Tag(..)), Some(_)) => vec![referenceable],
+        (_, Some(reference)) => vault.select_referenceables_for_reference(reference, path),
+        // the file is returned when nothing else is under the cursor; highlighting every link to the file is not useful
+        (Some(referenceable), None) if referenceable.get_range().is_some() => vec![referenceable],
+        _ => return None,
+    };
+
+    // Definitions are only in the current file if the referenceable is local. Tags are references to themselves, so they are only read
+    let definitions = referenceables
+        .iter()
+        .filter(|referenceable| {
+            !matches!(referenceable, Referenceable::Tag(..)) && referenceable.get_path() == path
+        })
+        .flat_map(|referenceable| referenceable.get_range())
+        .map(|range| DocumentHighlight {
+            range: *range,
+            kind: Some(DocumentHighlightKind::WRITE),
+        });
+
+    let references = referenceables
+        .iter()
+        .flat_map(|referenceable| vault.select_references_for_referenceable(referenceable))
+        .flatten()
+        .filter(|(reference_path, _)| *reference_path == path)
+        .map(|(_, reference)| DocumentHighlight {
+            range: *reference.data().range,
+            kind: Some(DocumentHighlightKind::READ),
+        });
+
+    Some(
+        definitions
+            .chain(references)
+            .unique_by(|highlight| {
+                (
+                    highlight.range.start.line,
+                    highlight.range.start.character,
+                    highlight.range.end.line,
+                    highlight.range.end.character,
+                )
+            })
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use itertools::Itertools;
+    use tower_lsp::lsp_types::{DocumentHighlightKind, Position};
+
+    use crate::{config::Settings, vault::Vault};
+
+    use super::document_highlight;
+
+    #[test]
+    fn highlights_definitions_and_references() {
+        let root_dir = Path::new("/vault");
+        let settings = Settings::for_vault(root_dir);
+        let vault = Vault::from_texts(
+            &settings,
+            root_dir,
+            &[(
+                "note.md",
+                "# Heading\n\nA paragraph ^block\n\nSee [[#Heading]] and [[#^block]] #tag and [^1]\n#tag again\n\n[^1]: The footnote\n",
+            )],
+        );
+        let path = root_dir.join("note.md");
+        let highlights = |line, character| {
+            document_highlight(&vault, Position { line, character }, &path)
+                .unwrap()
+                .into_iter()
+                .map(|highlight| {
+                    (
+                        highlight.range.start.line,
+                        highlight.range.start.character,
+                        highlight.range.end.character,
+                        highlight.kind.unwrap() == DocumentHighlightKind::WRITE,
+                    )
+                })
+                .sorted()
+                .collect_vec()
+        };
+
+        // (line, start, end, write)
+        assert_eq!(
+            highlights(0, 3),
+            vec![(0, 0, 9, true), (4, 4, 16, false)],
+            "heading"
+        );
+        assert_eq!(
+            highlights(5, 1),
+            vec![(4, 33, 37, false), (5, 0, 4, false)],
+            "tag"
+        );
+        assert_eq!(
+            highlights(2, 14),
+            vec![(2, 12, 18, true), (4, 21, 32, false)],
+            "indexed block"
+        );
+        assert_eq!(
+            highlights(4, 44),
+            vec![(4, 42, 46, false), (7, 0, 18, true)],
+            "footnote"
+        );
+    }
+}
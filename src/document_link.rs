@@ -0,0 +1,84 @@
+use std::path::Path;
+
+use tower_lsp::lsp_types::{DocumentLink, Url};
+
+use crate::vault::{is_url, Reference, Referenceable, Vault};
+
+pub fn document_link(vault: &Vault, path: &Path) -> Option<Vec<DocumentLink>> {
+    let md_file = vault.md_files.get(path)?;
+
+    let reference_links = md_file.references.iter().flat_map(|reference| {
+        let referenceable = match reference {
+            Reference::Tag(..) | Reference::Footnote(..) => return None,
+            _ => vault
+                .select_referenceables_for_reference(reference, path)
+                .into_iter()
+                .find(|referenceable| !referenceable.is_unresolved())?,
+        };
+
+        let target = match referenceable {
+            Referenceable::LinkRefDef(_, link_ref_def) => {
+                resolve_target(vault, path, &link_ref_def.url)?
+            }
+            Referenceable::Heading(..) | Referenceable::IndexedBlock(..) => {
+                let mut uri = Url::from_file_path(referenceable.get_path()).ok()?;
+                uri.set_fragment(Some(&format!(
+                    "L{}",
+                    referenceable.get_range()?.start.line + 1
+                )));
+                uri
+            }
+            _ => Url::from_file_path(referenceable.get_path()).ok()?,
+        };
+
+        Some(DocumentLink {
+            range: *reference.data().range,
+            target: Some(target),
+            tooltip: referenceable
+                .get_refname(vault.root_dir())
+                .map(|refname| refname.to_string()),
+            data: None,
+        })
+    });
+
+    let external_links = md_file.external_links.iter().flat_map(|link| {
+        Some(DocumentLink {
+            range: *link.range,
+            target: Some(resolve_target(vault, path, &link.target)?),
+            tooltip: Some(link.target.clone()),
+            data: None,
+        })
+    });
+
+    Some(reference_links.chain(external_links).collect())
+}
+
+fn strip_fragment(target: &str) -> &str {
+    target.split('#').next().unwrap_or(target)
+}
+
+fn resolve_target(vault: &Vault, path: &Path, target: &str) -> Option<Url> {
+    if is_url(target) {
+        return Url::parse(target).ok();
+    }
+
+    let file = strip_fragment(target).replace("%20", " ");
+
+    let relative_candidates = [
+        path.parent().map(|parent| parent.join(&file)),
+        Some(vault.root_dir().join(&file)),
+    ];
+
+    let resolved = relative_candidates
+        .into_iter()
+        .flatten()
+        .find(|candidate| candidate.is_file())
+        .or_else(|| {
+            // Obsidian resolves attachments by their file name anywhere in the vault
+            vault
+                .select_attachment(Path::new(&file).file_name()?)
+                .map(Path::to_path_buf)
+        })?;
+
+    Url::from_file_path(resolved).ok()
+}
@@ -0,0 +1,430 @@
+use std::{
+    collections::HashMap,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use pathdiff::diff_paths;
+
+use crate::{
+    config::{NewLinkFormat, Settings},
+    vault::{get_obsidian_ref_path, Reference},
+};
+
+/// The files of the vault by name, to link to each file by the shortest path that is unambiguous, as Obsidian does:
+/// `[[meeting]]` if there is only one `meeting.md`, otherwise `[[project-a/meeting]]`. The vault keeps them up to
+/// date, see `Vault::select_file_names`
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct FileNames {
+    /// Paths relative to the vault root, without the extension, by lowercase file name
+    by_name: HashMap<String, Vec<String>>,
+}
+
+impl FileNames {
+    pub fn new<'a>(root_dir: &Path, paths: impl IntoIterator<Item = &'a PathBuf>) -> FileNames {
+        paths
+            .into_iter()
+            .fold(FileNames::default(), |mut file_names, path| {
+                file_names.insert(root_dir, path);
+                file_names
+            })
+    }
+
+    /// Add the file at the path, if it is not in the file names yet
+    pub fn insert(&mut self, root_dir: &Path, path: &Path) {
+        let Some(ref_path) = get_obsidian_ref_path(root_dir, path) else {
+            return;
+        };
+
+        let paths = self
+            .by_name
+            .entry(file_name(&ref_path).to_lowercase())
+            .or_default();
+        if !paths.contains(&ref_path) {
+            paths.push(ref_path);
+        }
+    }
+
+    /// The shortest trailing part of the path of the file that no other file's path ends with
+    pub fn shortest_path(&self, root_dir: &Path, path: &Path) -> Option<String> {
+        let ref_path = get_obsidian_ref_path(root_dir, path)?;
+
+        let others = self
+            .by_name
+            .get(&file_name(&ref_path).to_lowercase())
+            .into_iter()
+            .flatten()
+            .filter(|other| **other != ref_path)
+            .collect::<Vec<_>>();
+
+        let components = ref_path.split('/').collect::<Vec<_>>();
+
+        let shortest = (1..=components.len())
+            .map(|n| components[components.len() - n..].join("/"))
+            .find(|suffix| !others.iter().any(|other| ends_with_path(other, suffix)))
+            .unwrap_or(ref_path);
+
+        Some(shortest)
+    }
+
+    /// The paths of the files that the file part of a link from a file in the folder `from_dir` matches,
+    /// as in `meeting`, `project-a/meeting`, `/projects/project-a/meeting` or `../project-a/meeting`
+    pub fn matching_paths(&self, link_path: &str, from_dir: &str) -> Vec<&str> {
+        let link_path = link_path.replace("%20", " ").replace(r"\ ", " ");
+
+        self.by_name
+            .get(&file_name(&link_path).to_lowercase())
+            .into_iter()
+            .flatten()
+            .filter(|path| matches_link_path(path, &link_path, from_dir))
+            .map(String::as_str)
+            .collect()
+    }
+}
+
+/// Whether the link path matches the path of a file, both relative to the vault root. Paths that start with `./` or `../`
+/// are relative to the folder of the linking file, or to the vault root; paths that start with `/` are relative to the vault root;
+/// other paths may be shortened to the end of the path
+pub fn matches_link_path(path: &str, link_path: &str, from_dir: &str) -> bool {
+    if link_path.starts_with("./") || link_path.starts_with("../") {
+        resolve_relative_path(from_dir, link_path).is_some_and(|resolved| resolved == path)
+            || resolve_relative_path("", link_path).is_some_and(|resolved| resolved == path)
+    } else if let Some(link_path) = link_path.strip_prefix('/') {
+        path == link_path
+    } else if link_path.contains('/') {
+        ends_with_path(path, link_path)
+    } else {
+        file_name(path).to_lowercase() == link_path.to_lowercase()
+    }
+}
+
+/// The path relative to the vault root of a link path relative to a folder; `../b/meeting` from `a/c` is `a/b/meeting`
+pub fn resolve_relative_path(from_dir: &str, link_path: &str) -> Option<String> {
+    let mut components = from_dir
+        .split('/')
+        .filter(|component| !component.is_empty())
+        .collect::<Vec<_>>();
+
+    for component in link_path.split('/') {
+        match component {
+            "" | "." => {}
+            ".." => {
+                components.pop()?;
+            }
+            component => components.push(component),
+        }
+    }
+
+    Some(components.join("/"))
+}
+
+/// The folder of the file relative to the vault root, as used for relative links: `projects/project-a`
+pub fn folder_ref_path(root_dir: &Path, path: &Path) -> Option<String> {
+    let folder = diff_paths(path.parent()?, root_dir)?;
+
+    Some(folder.to_str()?.replace('\\', "/"))
+}
+
+/// The file part of a new link from the file `from` to the file `to`, in the link format of the settings:
+/// `meeting` for the shortest path, `../project-a/meeting` for a relative path, or `projects/project-a/meeting` for an absolute path.
+/// The file that is linked to does not have to exist
+pub fn link_path(
+    settings: &Settings,
+    file_names: &FileNames,
+    root_dir: &Path,
+    from: &Path,
+    to: &Path,
+) -> Option<String> {
+    match settings.new_link_format {
+        NewLinkFormat::Shortest => file_names.shortest_path(root_dir, to),
+        NewLinkFormat::Absolute => get_obsidian_ref_path(root_dir, to),
+        NewLinkFormat::Relative => {
+            let relative = relative_link_path(from, to)?;
+
+            match relative.starts_with("../") {
+                true => Some(relative),
+                false => Some(format!("./{}", relative)),
+            }
+        }
+    }
+}
+
+/// The path of the file `to` relative to the folder of the file `from`, without the extension: `../project-a/meeting`
+pub fn relative_link_path(from: &Path, to: &Path) -> Option<String> {
+    let relative = diff_paths(to.with_extension(""), from.parent()?)?;
+
+    Some(relative.to_str()?.replace('\\', "/"))
+}
+
+/// A new link, `[[path#infile|display]]`, or `[display](path.md#infile)` if the settings use markdown links
+pub fn format_link(
+    settings: &Settings,
+    path: &str,
+    infile_ref: Option<&str>,
+    display: Option<&str>,
+) -> String {
+    match settings.use_markdown_links {
+        true => markdown_link(settings, path, infile_ref, display),
+        false => wiki_link(settings, path, infile_ref, display),
+    }
+}
+
+pub fn wiki_link(
+    settings: &Settings,
+    path: &str,
+    infile_ref: Option<&str>,
+    display: Option<&str>,
+) -> String {
+    let ext = match settings.include_md_extension_wikilink {
+        true => ".md",
+        false => "",
+    };
+
+    format!(
+        "[[{}{}{}{}]]",
+        path,
+        ext,
+        infile_ref
+            .map(|infile_ref| format!("#{}", infile_ref))
+            .unwrap_or_default(),
+        display
+            .map(|display| format!("|{}", display))
+            .unwrap_or_default()
+    )
+}
+
+/// Spaces in the path are encoded as `%20`; without display text, the link displays the heading or the file name
+pub fn markdown_link(
+    settings: &Settings,
+    path: &str,
+    infile_ref: Option<&str>,
+    display: Option<&str>,
+) -> String {
+    let ext = match settings.include_md_extension_md_link {
+        true => ".md",
+        false => "",
+    };
+
+    let display = display
+        .or(infile_ref)
+        .unwrap_or_else(|| file_name(path))
+        .to_string();
+
+    format!(
+        "[{}]({}{}{})",
+        display,
+        path.replace(' ', "%20"),
+        ext,
+        infile_ref
+            .map(|infile_ref| format!("#{}", infile_ref.replace(' ', "%20")))
+            .unwrap_or_default()
+    )
+}
+
+/// The characters of the file part of the link on its line, including a leading `./` or `/`;
+/// `[[meeting#Heading|display]]` and `[display](<./meeting.md#Heading>)` both have the file part `meeting`
+pub fn link_path_range(reference: &Reference, line: &[char]) -> Option<Range<usize>> {
+    let range = reference.data().range;
+    let link_start = range.start.character as usize;
+    let link = line.get(link_start..range.end.character as usize)?;
+
+    let opening = match reference {
+        Reference::WikiFileLink(..)
+        | Reference::WikiHeadingLink(..)
+        | Reference::WikiIndexedBlockLink(..) => ['[', '['],
+        _ => [']', '('],
+    };
+    let mut start = link.windows(2).position(|window| window == opening)? + 2;
+    if link.get(start) == Some(&'<') {
+        start += 1;
+    }
+
+    // the dots of a `./`, `../../` or `/` prefix do not end the path
+    let mut end = start + usize::from(link.get(start) == Some(&'/'));
+    loop {
+        end += match link[end..] {
+            ['.', '/', ..] => 2,
+            ['.', '.', '/', ..] => 3,
+            _ => break,
+        };
+    }
+    let end = link[end..]
+        .iter()
+        .position(|c| "#|].>)".contains(*c))
+        .map(|position| end + position)?;
+
+    Some(link_start + start..link_start + end)
+}
+
+/// The characters of the heading or block index, with the `^`, of the link on its line, after the `#`;
+/// `Heading` in both `[[meeting#Heading|display]]` and `[display](meeting.md#Heading)`
+pub fn link_infile_range(reference: &Reference, line: &[char]) -> Option<Range<usize>> {
+    let path_end = link_path_range(reference, line)?.end;
+    let link_end = reference.data().range.end.character as usize;
+
+    let start = match line.get(path_end..link_end)? {
+        ['#', ..] => path_end + 1,
+        ['.', 'm', 'd', '#', ..] => path_end + 4,
+        _ => return None,
+    };
+    let end = line[start..link_end]
+        .iter()
+        .position(|c| "|]>)".contains(*c))
+        .map(|position| start + position)?;
+
+    Some(start..end)
+}
+
+/// `a/b/meeting` ends with the path `b/meeting` but not with `ab/meeting`
+pub fn ends_with_path(path: &str, suffix: &str) -> bool {
+    path == suffix || path.ends_with(&format!("/{}", suffix))
+}
+
+pub fn file_name(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use itertools::Itertools;
+
+    use crate::{
+        config::{NewLinkFormat, Settings},
+        vault::{Reference, Vault},
+    };
+
+    use super::{
+        format_link, link_infile_range, link_path, link_path_range, resolve_relative_path,
+        FileNames,
+    };
+
+    #[test]
+    fn shortest_unique_paths() {
+        let root_dir = Path::new("/vault");
+        let paths = [
+            "work/project-a/meeting.md",
+            "work/project-b/meeting.md",
+            "work/todo.md",
+        ]
+        .map(|path| root_dir.join(path));
+        let mut file_names = FileNames::new(root_dir, &paths);
+        // files that the vault adds later, once
+        file_names.insert(root_dir, &root_dir.join("home/project-b/meeting.md"));
+        file_names.insert(root_dir, &root_dir.join("work/todo.md"));
+        let shortest = |path: &str| file_names.shortest_path(root_dir, &root_dir.join(path));
+
+        assert_eq!(shortest("work/todo.md"), Some("todo".to_string()));
+        assert_eq!(
+            shortest("work/project-a/meeting.md"),
+            Some("project-a/meeting".to_string())
+        );
+        assert_eq!(
+            shortest("home/project-b/meeting.md"),
+            Some("home/project-b/meeting".to_string())
+        );
+
+        assert_eq!(file_names.matching_paths("Meeting", "").len(), 3);
+        assert_eq!(file_names.matching_paths("project-b/meeting", "").len(), 2);
+        assert_eq!(
+            file_names.matching_paths("project-a/meeting", ""),
+            vec!["work/project-a/meeting"]
+        );
+        assert_eq!(file_names.matching_paths("/project-a/meeting", "").len(), 0);
+        assert_eq!(file_names.matching_paths("work/todo", "").len(), 1);
+        assert_eq!(
+            file_names.matching_paths("../project-b/meeting", "work/project-a"),
+            vec!["work/project-b/meeting"]
+        );
+        assert_eq!(
+            file_names.matching_paths("./meeting", "home/project-b"),
+            vec!["home/project-b/meeting"]
+        );
+
+        assert_eq!(
+            resolve_relative_path("a/c", "../b/meeting"),
+            Some("a/b/meeting".to_string())
+        );
+        assert_eq!(resolve_relative_path("", "../meeting"), None);
+    }
+
+    #[test]
+    fn configured_link_paths() {
+        let root_dir = Path::new("/vault");
+        let mut settings = Settings::for_vault(root_dir);
+        let vault = Vault::from_texts(
+            &settings,
+            root_dir,
+            &[
+                ("work/project-a/todo.md", ""),
+                ("work/project-b/meeting notes.md", ""),
+            ],
+        );
+        let file_names = vault.select_file_names();
+        let from = root_dir.join("work/project-a/todo.md");
+        let to = root_dir.join("work/project-b/meeting notes.md");
+        let link = |settings: &Settings| link_path(settings, file_names, root_dir, &from, &to);
+
+        settings.new_link_format = NewLinkFormat::Shortest;
+        assert_eq!(link(&settings), Some("meeting notes".to_string()));
+
+        settings.new_link_format = NewLinkFormat::Absolute;
+        assert_eq!(
+            link(&settings),
+            Some("work/project-b/meeting notes".to_string())
+        );
+
+        settings.new_link_format = NewLinkFormat::Relative;
+        let relative = link(&settings).unwrap();
+        assert_eq!(relative, "../project-b/meeting notes");
+
+        settings.use_markdown_links = true;
+        settings.include_md_extension_md_link = true;
+        assert_eq!(
+            format_link(&settings, &relative, Some("Agenda"), None),
+            "[Agenda](../project-b/meeting%20notes.md#Agenda)"
+        );
+    }
+
+    #[test]
+    fn link_path_ranges() {
+        let path_of = |text: &str| {
+            let reference = Reference::new(text, "file").next().unwrap();
+            let line = text.chars().collect_vec();
+            let range = link_path_range(&reference, &line).unwrap();
+
+            String::from_iter(&line[range])
+        };
+
+        assert_eq!(path_of("see [[meeting]]"), "meeting");
+        assert_eq!(path_of("see ![[meeting#Heading|display]]"), "meeting");
+        assert_eq!(
+            path_of("see [display](meeting%20notes.md#^block)"),
+            "meeting%20notes"
+        );
+        assert_eq!(
+            path_of("see [display](<./meeting notes.md>)"),
+            "./meeting notes"
+        );
+        assert_eq!(
+            path_of("see [[../project/meeting#Heading]]"),
+            "../project/meeting"
+        );
+
+        let infile_of = |text: &str| {
+            let reference = Reference::new(text, "file").next().unwrap();
+            let line = text.chars().collect_vec();
+            let range = link_infile_range(&reference, &line).unwrap();
+
+            String::from_iter(&line[range])
+        };
+
+        assert_eq!(infile_of("see [[#Heading]]"), "Heading");
+        assert_eq!(infile_of("see [[meeting#^block|display]]"), "^block");
+        assert_eq!(
+            infile_of("see [display](<meeting notes.md#Next Steps>)"),
+            "Next Steps"
+        );
+    }
+}
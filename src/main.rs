@@ -1,9 +1,9 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::ops::{Deref, DerefMut};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
-use completion::get_completions;
+use completion::{get_completions, resolve_completion};
 use config::{EmbeddedBlockTransclusionLength, Settings};
 use diagnostics::diagnostics;
 use itertools::Itertools;
@@ -20,21 +20,32 @@ use tower_lsp::lsp_types::*;
 use tower_lsp::{Client, LanguageServer, LspService, Server};
 use vault::{Preview, Rangeable, Reference, Vault};
 
+mod block_ids;
 mod codeactions;
 mod codelens;
 mod commands;
 mod completion;
 mod config;
+mod convert_links;
 mod daily;
 mod diagnostics;
+mod document_highlight;
+mod document_link;
 mod gotodef;
 mod hover;
+mod link_format;
 mod macros;
+mod periodic;
 mod references;
 mod rename;
+mod search;
+mod selection_range;
 mod symbol;
+mod tasks;
+mod templates;
 mod tokens;
 mod ui;
+mod unique_note;
 mod vault;
 
 #[derive(Debug)]
@@ -43,6 +54,8 @@ struct Backend {
     vault: Arc<RwLock<Option<Vault>>>,
     opened_files: Arc<RwLock<HashSet<PathBuf>>>,
     settings: Arc<RwLock<Option<Settings>>>,
+    /// The last full semantic tokens sent for each file, used to compute deltas
+    semantic_tokens: Arc<RwLock<HashMap<PathBuf, SemanticTokens>>>,
 }
 
 struct TextDocumentItem {
@@ -261,6 +274,48 @@ impl Backend {
         callback(settings)
     }
 
+    /// Store the tokens as the latest for the file under a new result id
+    async fn cache_semantic_tokens(
+        &self,
+        path: PathBuf,
+        data: Vec<SemanticToken>,
+    ) -> SemanticTokens {
+        let mut cache = self.semantic_tokens.write().await;
+
+        let result_id = cache
+            .get(&path)
+            .and_then(|previous| previous.result_id.as_ref()?.parse::<u64>().ok())
+            .map_or(0, |id| id + 1)
+            .to_string();
+
+        let tokens = SemanticTokens {
+            result_id: Some(result_id),
+            data,
+        };
+        cache.insert(path, tokens.clone());
+
+        tokens
+    }
+
+    /// Forget the tokens of closed, deleted or renamed files
+    /// Keep the attachments of the vault current for document links, without reconstructing the vault
+    async fn update_attachments(&self, removed: &[PathBuf], added: &[PathBuf]) {
+        let _ = self
+            .bind_vault_mut(|vault| {
+                vault.update_attachments(removed, added);
+                Ok(())
+            })
+            .await;
+    }
+
+    async fn evict_semantic_tokens(&self, paths: impl IntoIterator<Item = PathBuf>) {
+        let mut cache = self.semantic_tokens.write().await;
+
+        for path in paths {
+            cache.remove(&path);
+        }
+    }
+
     async fn bind_opened_files<T>(
         &self,
         callback: impl Fn(&HashSet<PathBuf>) -> Result<T>,
@@ -320,15 +375,17 @@ impl LanguageServer for Backend {
         let mut value = self.vault.write().await;
         *value = Some(vault);
 
+        let legend = tokens::legend(&read_settings);
         let mut settings = self.settings.write().await;
         *settings = Some(read_settings);
 
         let file_op_reg = FileOperationRegistrationOptions {
             filters: std::iter::once(FileOperationFilter {
+                // attachments too, for document links
                 pattern: FileOperationPattern {
                     options: None,
-                    glob: "**/*.md".into(),
-                    matches: None,
+                    glob: "**/*".into(),
+                    matches: Some(FileOperationPatternKind::File),
                 },
                 ..Default::default()
             })
@@ -342,13 +399,14 @@ impl LanguageServer for Backend {
                     TextDocumentSyncKind::FULL,
                 )),
                 completion_provider: Some(CompletionOptions {
-                    resolve_provider: Some(false),
+                    resolve_provider: Some(true),
                     trigger_characters: Some(vec![
                         "[".into(),
                         " ".into(),
                         "(".into(),
                         "#".into(),
                         ">".into(),
+                        "@".into(),
                     ]),
                     work_done_progress_options: Default::default(),
                     all_commit_characters: None,
@@ -358,11 +416,20 @@ impl LanguageServer for Backend {
                 inlay_hint_provider: Some(OneOf::Left(true)),
                 definition_provider: Some(OneOf::Left(true)),
                 references_provider: Some(OneOf::Left(true)),
-                rename_provider: Some(OneOf::Left(true)),
+                document_highlight_provider: Some(OneOf::Left(true)),
+                document_link_provider: Some(DocumentLinkOptions {
+                    resolve_provider: None,
+                    work_done_progress_options: Default::default(),
+                }),
+                rename_provider: Some(OneOf::Right(RenameOptions {
+                    prepare_provider: Some(true),
+                    work_done_progress_options: Default::default(),
+                })),
                 hover_provider: Some(HoverProviderCapability::Simple(true)),
                 document_symbol_provider: Some(OneOf::Left(true)),
                 workspace_symbol_provider: Some(OneOf::Left(true)),
                 code_action_provider: Some(CodeActionProviderCapability::Simple(true)),
+                selection_range_provider: Some(SelectionRangeProviderCapability::Simple(true)),
                 workspace: Some(WorkspaceServerCapabilities {
                     file_operations: Some(WorkspaceFileOperationsServerCapabilities {
                         did_create: Some(file_op_reg.clone()),
@@ -378,7 +445,15 @@ impl LanguageServer for Backend {
                 execute_command_provider: Some(ExecuteCommandOptions {
                     commands: vec![
                         "apply_edits".into(),
+                        "convertLinks".into(),
+                        "dailyNotes".into(),
                         "jump".into(),
+                        "newFromTemplate".into(),
+                        "newUniqueNote".into(),
+                        "previousDailyNote".into(),
+                        "search".into(),
+                        "nextDailyNote".into(),
+                        "tasks".into(),
                         "tomorrow".into(),
                         "today".into(),
                         "yesterday".into(),
@@ -396,24 +471,24 @@ impl LanguageServer for Backend {
                         "next tuesday".into(),
                         "next wednesday".into(),
                         "next thursday".into(),
+                        "this week".into(),
+                        "last week".into(),
+                        "next week".into(),
+                        "this month".into(),
+                        "last month".into(),
+                        "next month".into(),
+                        "this year".into(),
+                        "last year".into(),
+                        "next year".into(),
                     ],
                     ..Default::default()
                 }),
                 semantic_tokens_provider: Some(
                     SemanticTokensServerCapabilities::SemanticTokensOptions(
                         SemanticTokensOptions {
-                            full: Some(SemanticTokensFullOptions::Bool(true)),
-                            range: Some(false),
-                            legend: SemanticTokensLegend {
-                                token_types: vec![
-                                    SemanticTokenType::DECORATOR,
-                                    SemanticTokenType::COMMENT,
-                                ],
-                                token_modifiers: vec![
-                                    SemanticTokenModifier::DECLARATION,
-                                    SemanticTokenModifier::DEPRECATED,
-                                ],
-                            },
+                            full: Some(SemanticTokensFullOptions::Delta { delta: Some(true) }),
+                            range: Some(true),
+                            legend,
                             ..Default::default()
                         },
                     ),
@@ -510,6 +585,10 @@ impl LanguageServer for Backend {
     }
 
     async fn did_close(&self, params: DidCloseTextDocumentParams) {
+        if let Ok(path) = params.text_document.uri.to_file_path() {
+            self.evict_semantic_tokens([path]).await;
+        }
+
         let removed_file = self
             .bind_opened_files_mut(|files| {
                 let path = params_path!(params)?;
@@ -533,6 +612,43 @@ impl LanguageServer for Backend {
         .await;
     }
 
+    async fn did_create_files(&self, params: CreateFilesParams) {
+        let paths = params
+            .files
+            .iter()
+            .flat_map(|file| Url::parse(&file.uri).ok()?.to_file_path().ok())
+            .collect::<Vec<_>>();
+
+        self.update_attachments(&[], &paths).await;
+    }
+
+    async fn did_rename_files(&self, params: RenameFilesParams) {
+        let old_paths = params
+            .files
+            .iter()
+            .flat_map(|file| Url::parse(&file.old_uri).ok()?.to_file_path().ok())
+            .collect::<Vec<_>>();
+        let new_paths = params
+            .files
+            .iter()
+            .flat_map(|file| Url::parse(&file.new_uri).ok()?.to_file_path().ok())
+            .collect::<Vec<_>>();
+
+        self.update_attachments(&old_paths, &new_paths).await;
+        self.evict_semantic_tokens(old_paths).await;
+    }
+
+    async fn did_delete_files(&self, params: DeleteFilesParams) {
+        let paths = params
+            .files
+            .iter()
+            .flat_map(|file| Url::parse(&file.uri).ok()?.to_file_path().ok())
+            .collect::<Vec<_>>();
+
+        self.update_attachments(&paths, &[]).await;
+        self.evict_semantic_tokens(paths).await;
+    }
+
     async fn did_change_watched_files(&self, _params: DidChangeWatchedFilesParams) {
         self.reconstruct_vault().await
     }
@@ -563,6 +679,29 @@ impl LanguageServer for Backend {
         .await
     }
 
+    async fn document_highlight(
+        &self,
+        params: DocumentHighlightParams,
+    ) -> Result<Option<Vec<DocumentHighlight>>> {
+        self.bind_vault(|vault| {
+            let path = params_path!(params.text_document_position_params)?;
+            Ok(document_highlight::document_highlight(
+                vault,
+                params.text_document_position_params.position,
+                &path,
+            ))
+        })
+        .await
+    }
+
+    async fn document_link(&self, params: DocumentLinkParams) -> Result<Option<Vec<DocumentLink>>> {
+        self.bind_vault(|vault| {
+            let path = params_path!(params)?;
+            Ok(document_link::document_link(vault, &path))
+        })
+        .await
+    }
+
     async fn completion(&self, params: CompletionParams) -> Result<Option<CompletionResponse>> {
         self.client
             .log_message(MessageType::WARNING, "Completions Started")
@@ -595,6 +734,11 @@ impl LanguageServer for Backend {
         res
     }
 
+    async fn completion_resolve(&self, params: CompletionItem) -> Result<CompletionItem> {
+        self.bind_vault(|vault| Ok(resolve_completion(vault, params)))
+            .await
+    }
+
     async fn execute_command(&self, params: ExecuteCommandParams) -> Result<Option<Value>> {
         let settings = self.bind_settings(|settings| Ok(settings.clone())).await?;
         let root_dir = self
@@ -615,6 +759,42 @@ impl LanguageServer for Backend {
 
                 Ok(None)
             }
+            ExecuteCommandParams { command, .. } if *command == *"convertLinks" => {
+                let args = params
+                    .arguments
+                    .into_iter()
+                    .next()
+                    .ok_or_else(|| Error::invalid_params("Missing the style to convert links to"))
+                    .and_then(|arg| {
+                        serde_json::from_value::<convert_links::ConvertLinks>(arg)
+                            .map_err(|e| Error::invalid_params(e.to_string()))
+                    })?;
+                let only_path = match &args.uri {
+                    Some(uri) => Some(
+                        uri.to_file_path()
+                            .map_err(|_| Error::invalid_params("The uri is not a file"))?,
+                    ),
+                    None => None,
+                };
+
+                let edit = self
+                    .bind_vault(|vault| {
+                        let paths =
+                            vault.md_files.keys().map(PathBuf::as_path).filter(|path| {
+                                only_path.as_deref().is_none_or(|only| only == *path)
+                            });
+
+                        Ok(convert_links::convert_links_edit(
+                            vault, &settings, paths, args.to,
+                        ))
+                    })
+                    .await?;
+
+                self.client
+                    .apply_edit(edit)
+                    .await
+                    .map(|response| Some(response.applied.into()))
+            }
             ExecuteCommandParams { command, .. } if *command == *"jump" => {
                 let jump_to = params.arguments.first().and_then(|val| val.as_str());
                 let settings = self
@@ -625,6 +805,115 @@ impl LanguageServer for Backend {
                     .await?;
                 commands::jump(&self.client, &root_dir, &settings, jump_to).await
             }
+            ExecuteCommandParams { command, .. } if *command == *"newFromTemplate" => {
+                let args = match params.arguments.into_iter().next() {
+                    Some(arg) => serde_json::from_value::<templates::NewFromTemplate>(arg)
+                        .map_err(|e| Error::invalid_params(e.to_string()))?,
+                    None => templates::NewFromTemplate::default(),
+                };
+
+                commands::new_from_template(&self.client, &root_dir, &settings, args).await
+            }
+            ExecuteCommandParams { command, .. } if *command == *"newUniqueNote" => {
+                let args = match params.arguments.into_iter().next() {
+                    Some(arg) => serde_json::from_value::<unique_note::NewUniqueNote>(arg)
+                        .map_err(|e| Error::invalid_params(e.to_string()))?,
+                    None => unique_note::NewUniqueNote::default(),
+                };
+
+                commands::new_unique_note(&self.client, &root_dir, &settings, args).await
+            }
+            ExecuteCommandParams { command, .. }
+                if *command == *"previousDailyNote" || *command == *"nextDailyNote" =>
+            {
+                let direction = match command.as_str() {
+                    "previousDailyNote" => daily::Direction::Previous,
+                    _ => daily::Direction::Next,
+                };
+                let args = params
+                    .arguments
+                    .into_iter()
+                    .next()
+                    .ok_or_else(|| Error::invalid_params("Missing the uri of the daily note"))
+                    .and_then(|arg| {
+                        serde_json::from_value::<commands::AdjacentDailyNote>(arg)
+                            .map_err(|e| Error::invalid_params(e.to_string()))
+                    })?;
+                let path = args
+                    .uri
+                    .to_file_path()
+                    .map_err(|_| Error::invalid_params("The uri is not a file"))?;
+
+                if daily::daily_note_date(&settings, &root_dir, &path).is_none() {
+                    return Err(Error::invalid_params(format!(
+                        "{:?} is not a daily note in the format {:?}",
+                        path, settings.dailynote
+                    )));
+                }
+
+                let adjacent = self
+                    .bind_vault(|vault| {
+                        Ok(daily::adjacent_daily_note(
+                            vault,
+                            &settings,
+                            &path,
+                            direction,
+                            args.create,
+                        ))
+                    })
+                    .await?;
+
+                commands::open_adjacent_daily_note(
+                    &self.client,
+                    &root_dir,
+                    &settings,
+                    direction,
+                    adjacent,
+                )
+                .await
+            }
+            ExecuteCommandParams { command, .. } if *command == *"dailyNotes" => {
+                let query = match params.arguments.into_iter().next() {
+                    Some(arg) => serde_json::from_value::<daily::DailyNotesQuery>(arg)
+                        .map_err(|e| Error::invalid_params(e.to_string()))?,
+                    None => daily::DailyNotesQuery::default(),
+                };
+
+                self.bind_vault(|vault| {
+                    serde_json::to_value(daily::daily_notes(vault, &settings, &query))
+                        .map(Some)
+                        .map_err(|_| Error::internal_error())
+                })
+                .await
+            }
+            ExecuteCommandParams { command, .. } if *command == *"search" => {
+                let query = match params.arguments.into_iter().next() {
+                    Some(arg) => serde_json::from_value::<search::SearchQuery>(arg)
+                        .map_err(|e| Error::invalid_params(e.to_string()))?,
+                    None => search::SearchQuery::default(),
+                };
+
+                self.bind_vault(|vault| {
+                    serde_json::to_value(search::search(vault, &settings, &query))
+                        .map(Some)
+                        .map_err(|_| Error::internal_error())
+                })
+                .await
+            }
+            ExecuteCommandParams { command, .. } if *command == *"tasks" => {
+                let query = match params.arguments.into_iter().next() {
+                    Some(arg) => serde_json::from_value::<tasks::TaskQuery>(arg)
+                        .map_err(|e| Error::invalid_params(e.to_string()))?,
+                    None => tasks::TaskQuery::default(),
+                };
+
+                self.bind_vault(|vault| {
+                    serde_json::to_value(tasks::query_tasks(vault, &query))
+                        .map(Some)
+                        .map_err(|_| Error::internal_error())
+                })
+                .await
+            }
             ExecuteCommandParams { command, .. } => {
                 jump_to_specific(&command, &self.client, &root_dir, &settings).await
             } // _ => Ok(None),
@@ -655,14 +944,30 @@ impl LanguageServer for Backend {
         &self,
         params: WorkspaceSymbolParams,
     ) -> Result<Option<Vec<SymbolInformation>>> {
-        self.bind_vault(|vault| Ok(workspace_symbol(vault, &params)))
-            .await
+        let settings = self.bind_settings(|settings| Ok(settings.clone())).await?;
+        // `/query` searches the contents of the notes
+        self.bind_vault(|vault| match params.query.strip_prefix('/') {
+            Some(query) => Ok(Some(search::search_symbols(vault, &settings, query))),
+            None => Ok(workspace_symbol(vault, &params)),
+        })
+        .await
+    }
+
+    async fn prepare_rename(
+        &self,
+        params: TextDocumentPositionParams,
+    ) -> Result<Option<PrepareRenameResponse>> {
+        self.bind_vault(|vault| {
+            let path = params_path!(params)?;
+            Ok(rename::prepare_rename(vault, &params, &path))
+        })
+        .await
     }
 
     async fn rename(&self, params: RenameParams) -> Result<Option<WorkspaceEdit>> {
         self.bind_vault(|vault| {
             let path = params_position_path!(params)?;
-            Ok(rename::rename(vault, &params, &path))
+            rename::rename(vault, &params, &path)
         })
         .await
     }
@@ -677,6 +982,17 @@ impl LanguageServer for Backend {
         .await
     }
 
+    async fn selection_range(
+        &self,
+        params: SelectionRangeParams,
+    ) -> Result<Option<Vec<SelectionRange>>> {
+        self.bind_vault(|vault| {
+            let path = params_path!(params)?;
+            Ok(selection_range::selection_range(vault, &params, &path))
+        })
+        .await
+    }
+
     async fn semantic_tokens_full(
         &self,
         params: SemanticTokensParams,
@@ -686,13 +1002,16 @@ impl LanguageServer for Backend {
         let timer = std::time::Instant::now();
 
         let path = params_path!(params)?;
-        let res = self
-            .bind_vault(|vault| {
-                Ok(tokens::semantic_tokens_full(
-                    vault, &path, params, &settings,
-                ))
-            })
-            .await;
+        let data = self
+            .bind_vault(|vault| Ok(tokens::semantic_tokens_full(vault, &path, &settings)))
+            .await?;
+
+        let res = match data {
+            Some(data) => Some(SemanticTokensResult::Tokens(
+                self.cache_semantic_tokens(path, data).await,
+            )),
+            None => None,
+        };
 
         let elapsed = timer.elapsed();
 
@@ -703,7 +1022,56 @@ impl LanguageServer for Backend {
             )
             .await;
 
-        return res;
+        Ok(res)
+    }
+
+    async fn semantic_tokens_full_delta(
+        &self,
+        params: SemanticTokensDeltaParams,
+    ) -> Result<Option<SemanticTokensFullDeltaResult>> {
+        let settings = self.bind_settings(|settings| Ok(settings.clone())).await?;
+
+        let path = params_path!(params)?;
+        let Some(data) = self
+            .bind_vault(|vault| Ok(tokens::semantic_tokens_full(vault, &path, &settings)))
+            .await?
+        else {
+            return Ok(None);
+        };
+
+        let previous = self.semantic_tokens.read().await.get(&path).cloned();
+        let current = self.cache_semantic_tokens(path, data).await;
+
+        // a delta can only be computed against the tokens that the client has
+        match previous {
+            Some(previous) if previous.result_id == Some(params.previous_result_id) => Ok(Some(
+                SemanticTokensFullDeltaResult::TokensDelta(SemanticTokensDelta {
+                    edits: tokens::semantic_tokens_delta(&previous.data, &current.data),
+                    result_id: current.result_id,
+                }),
+            )),
+            _ => Ok(Some(SemanticTokensFullDeltaResult::Tokens(current))),
+        }
+    }
+
+    async fn semantic_tokens_range(
+        &self,
+        params: SemanticTokensRangeParams,
+    ) -> Result<Option<SemanticTokensRangeResult>> {
+        let settings = self.bind_settings(|settings| Ok(settings.clone())).await?;
+
+        self.bind_vault(|vault| {
+            let path = params_path!(params)?;
+            Ok(
+                tokens::semantic_tokens_range(vault, &path, params.range, &settings).map(|data| {
+                    SemanticTokensRangeResult::Tokens(SemanticTokens {
+                        result_id: None,
+                        data,
+                    })
+                }),
+            )
+        })
+        .await
     }
 
     async fn inlay_hint(&self, params: InlayHintParams) -> Result<Option<Vec<InlayHint>>> {
@@ -811,7 +1179,7 @@ impl LanguageServer for Backend {
 async fn jump_to_specific(
     day: &str,
     client: &Client,
-    root_dir: &PathBuf,
+    root_dir: &Path,
     settings: &Settings,
 ) -> Result<Option<Value>> {
     commands::jump(client, root_dir, settings, Some(day)).await
@@ -826,6 +1194,15 @@ async fn main() {
         return;
     }
 
+    let args = env::args().collect::<Vec<_>>();
+    if args.get(1).map(|arg| arg.as_str()) == Some("tasks") {
+        if let Err(e) = tasks::cli(&args[2..]) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     let stdin = tokio::io::stdin();
     let stdout = tokio::io::stdout();
 
@@ -834,6 +1211,7 @@ async fn main() {
         vault: Arc::new(None.into()),
         opened_files: Arc::new(HashSet::new().into()),
         settings: Arc::new(None.into()),
+        semantic_tokens: Arc::new(HashMap::new().into()),
     });
     Server::new(stdin, stdout, socket).serve(service).await;
 }
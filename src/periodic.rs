@@ -0,0 +1,315 @@
+use std::{
+    collections::HashMap,
+    fmt::Write as _,
+    fs::File,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use chrono::{Datelike, Days, Duration, Local, Months, NaiveDate, NaiveDateTime, NaiveTime};
+
+use crate::{
+    config::Settings,
+    daily::formatted_note_path,
+    templates::{expand_template, read_template},
+};
+
+/// The kinds of periodic notes; daily notes are the notes of the `Day` period
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Period {
+    Day,
+    Week,
+    Month,
+    Year,
+}
+
+impl Period {
+    pub const ALL: [Period; 4] = [Period::Day, Period::Week, Period::Month, Period::Year];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Period::Day => "day",
+            Period::Week => "week",
+            Period::Month => "month",
+            Period::Year => "year",
+        }
+    }
+
+    pub fn format(self, settings: &Settings) -> &str {
+        match self {
+            Period::Day => &settings.dailynote,
+            Period::Week => &settings.weeklynote,
+            Period::Month => &settings.monthlynote,
+            Period::Year => &settings.yearlynote,
+        }
+    }
+
+    pub fn folder(self, settings: &Settings) -> &str {
+        match self {
+            Period::Day => &settings.daily_notes_folder,
+            Period::Week => &settings.weekly_notes_folder,
+            Period::Month => &settings.monthly_notes_folder,
+            Period::Year => &settings.yearly_notes_folder,
+        }
+    }
+
+    pub fn template(self, settings: &Settings) -> &str {
+        match self {
+            Period::Day => &settings.daily_note_template,
+            Period::Week => &settings.weekly_note_template,
+            Period::Month => &settings.monthly_note_template,
+            Period::Year => &settings.yearly_note_template,
+        }
+    }
+
+    /// The first day of the period of the date; weeks start on monday, as ISO weeks do
+    pub fn start(self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Period::Day => date,
+            Period::Week => date
+                .checked_sub_days(Days::new(date.weekday().num_days_from_monday() as u64))
+                .unwrap_or(date),
+            Period::Month => date.with_day(1).unwrap_or(date),
+            Period::Year => date.with_ordinal(1).unwrap_or(date),
+        }
+    }
+
+    /// The first day of the period `n` periods after the period of the date
+    pub fn offset(self, date: NaiveDate, n: i32) -> Option<NaiveDate> {
+        let start = self.start(date);
+
+        let months = |months: i32| match months {
+            0.. => start.checked_add_months(Months::new(months as u32)),
+            _ => start.checked_sub_months(Months::new(months.unsigned_abs())),
+        };
+
+        match self {
+            Period::Day => start.checked_add_signed(Duration::try_days(n as i64)?),
+            Period::Week => start.checked_add_signed(Duration::try_weeks(n as i64)?),
+            Period::Month => months(n),
+            Period::Year => months(n.checked_mul(12)?),
+        }
+    }
+}
+
+/// The file of the periodic note for the period of the date; the file may not exist yet
+pub fn note_path(settings: &Settings, root_dir: &Path, period: Period, date: NaiveDate) -> PathBuf {
+    formatted_note_path(
+        period.format(settings),
+        &root_dir.join(period.folder(settings)),
+        NaiveDateTime::new(period.start(date), NaiveTime::MIN),
+    )
+}
+
+/// The period and its first day for a link to a periodic note: either the path relative to the folder of the period,
+/// without the extension, or only the file name, which is how Obsidian links to notes in nested folders
+pub fn parse_note_name(settings: &Settings, name: &str) -> Option<(Period, NaiveDate)> {
+    Period::ALL.into_iter().find_map(|period| {
+        let format = period.format(settings);
+
+        parse_formatted_name(format, name)
+            .or_else(|| parse_formatted_name(file_name_format(format), name))
+            .map(|date| (period, period.start(date)))
+    })
+}
+
+/// The period and its first day for the file of a periodic note. Formats with folders, such as `%Y/%m/%Y-%m-%d`,
+/// are matched against the path relative to the folder of the period; other formats only against the file name
+pub fn parse_note_path(
+    settings: &Settings,
+    root_dir: &Path,
+    path: &Path,
+) -> Option<(Period, NaiveDate)> {
+    Period::ALL.into_iter().find_map(|period| {
+        let format = period.format(settings);
+
+        let name = match format.contains('/') {
+            true => path
+                .strip_prefix(root_dir.join(period.folder(settings)))
+                .ok()?
+                .with_extension("")
+                .to_str()?
+                .replace('\\', "/"),
+            false => path.file_stem()?.to_str()?.to_string(),
+        };
+
+        parse_formatted_name(format, &name).map(|date| (period, period.start(date)))
+    })
+}
+
+/// The part of the format for the file name, without the folders; `%Y-%m-%d` for `%Y/%m/%Y-%m-%d`
+pub fn file_name_format(format: &str) -> &str {
+    format.rsplit('/').next().unwrap_or(format)
+}
+
+/// Formats without a day, such as `%G-W%V` or `%Y-%m`, are completed with the first day of the period.
+/// The name has to be exactly what the format produces for the date, so that a monthly format does not match a daily note;
+/// a format that can not be written for a date, such as one with a time or an offset, matches no name
+pub fn parse_formatted_name(format: &str, name: &str) -> Option<NaiveDate> {
+    const COMPLETIONS: [(&str, &str); 4] =
+        [("", ""), (" %u", " 1"), (" %d", " 1"), (" %m %d", " 1 1")];
+
+    COMPLETIONS
+        .iter()
+        .find_map(|(format_suffix, name_suffix)| {
+            NaiveDate::parse_from_str(
+                &format!("{}{}", name, name_suffix),
+                &format!("{}{}", format, format_suffix),
+            )
+            .ok()
+        })
+        .filter(|date| {
+            let mut formatted = String::new();
+            write!(formatted, "{}", date.format(format)).is_ok() && formatted == name
+        })
+}
+
+/// `today`, `tomorrow`, `next tuesday` ... for days within a week, and `this week`, `last month`, `next year` ... for the other periods
+pub fn relative_name(period: Period, date: NaiveDate, today: NaiveDate) -> Option<String> {
+    match period {
+        Period::Day => match (date - today).num_days() {
+            0 => Some("today".to_string()),
+            1 => Some("tomorrow".to_string()),
+            2..=7 => Some(format!("next {}", date.format("%A"))),
+            -1 => Some("yesterday".to_string()),
+            -7..=-1 => Some(format!("last {}", date.format("%A"))),
+            _ => None,
+        },
+        _ => {
+            let start = period.start(date);
+
+            [(0, "this"), (-1, "last"), (1, "next")]
+                .into_iter()
+                .find(|(n, _)| period.offset(today, *n) == Some(start))
+                .map(|(_, relation)| format!("{} {}", relation, period.name()))
+        }
+    }
+}
+
+/// `this week`, `last month`, `next year` ...; relative days are parsed with fuzzydate instead
+pub fn parse_relative_name(name: &str, today: NaiveDate) -> Option<(Period, NaiveDate)> {
+    let name = name.trim().to_lowercase();
+    let (relation, period_name) = name.split_once(' ')?;
+
+    let n = match relation {
+        "this" => 0,
+        "last" | "previous" => -1,
+        "next" => 1,
+        _ => return None,
+    };
+
+    let period = Period::ALL
+        .into_iter()
+        .find(|period| *period != Period::Day && period.name() == period_name.trim())?;
+
+    Some((period, period.offset(today, n)?))
+}
+
+/// The text of a new periodic note: the template of the period expanded for the note, or nothing if there is no template
+pub fn new_note_text(
+    root_dir: &Path,
+    settings: &Settings,
+    period: Period,
+    path: &Path,
+    date: NaiveDate,
+) -> String {
+    let title = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    // the date is the note's, the time is when it is created
+    let datetime = NaiveDateTime::new(date, Local::now().time());
+
+    read_template(root_dir, period.template(settings))
+        .map(|template| expand_template(&template, &title, datetime, &HashMap::new()))
+        .unwrap_or_default()
+}
+
+/// Create the periodic note at the path, with the template of the period, if it does not exist
+pub fn create_note(
+    root_dir: &Path,
+    settings: &Settings,
+    period: Period,
+    path: &Path,
+    date: NaiveDate,
+) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let mut file = File::create_new(path)?;
+    file.write_all(new_note_text(root_dir, settings, period, path, date).as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::{
+        file_name_format, parse_formatted_name, parse_relative_name, relative_name, Period,
+    };
+
+    #[test]
+    fn periodic_names() {
+        let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+        let today = date(2026, 10, 18); // a sunday in 2026-W42
+
+        assert_eq!(
+            parse_formatted_name("%G-W%V", "2026-W42"),
+            Some(date(2026, 10, 12))
+        );
+        assert_eq!(
+            parse_formatted_name("%Y-%m", "2026-10"),
+            Some(date(2026, 10, 1))
+        );
+        assert_eq!(parse_formatted_name("%Y", "2026"), Some(date(2026, 1, 1)));
+        assert_eq!(parse_formatted_name("%Y-%m-%d", "2026-10-18"), Some(today));
+        assert_eq!(parse_formatted_name("%Y-%m", "2026-10-18"), None);
+        assert_eq!(parse_formatted_name("%Y-%m-%d", "2026-W42"), None);
+        assert_eq!(
+            parse_formatted_name("%Y/%m/%Y-%m-%d", "2026/10/2026-10-18"),
+            Some(today)
+        );
+        assert_eq!(
+            parse_formatted_name(file_name_format("%Y/%m/%Y-%m-%d"), "2026-10-18"),
+            Some(today)
+        );
+        assert_eq!(
+            parse_formatted_name("%Y/%m/%Y-%m-%d", "2026/09/2026-10-18"),
+            None
+        );
+        assert_eq!(
+            parse_formatted_name("%Y-%m-%d %H:%M", "2026-10-18 09:05"),
+            None
+        );
+
+        assert_eq!(
+            parse_relative_name("next week", today),
+            Some((Period::Week, date(2026, 10, 19)))
+        );
+        assert_eq!(
+            parse_relative_name("last month", today),
+            Some((Period::Month, date(2026, 9, 1)))
+        );
+        assert_eq!(
+            parse_relative_name("this year", today),
+            Some((Period::Year, date(2026, 1, 1)))
+        );
+        assert_eq!(parse_relative_name("next tuesday", today), None);
+
+        assert_eq!(
+            relative_name(Period::Week, date(2026, 10, 5), today),
+            Some("last week".to_string())
+        );
+        assert_eq!(
+            relative_name(Period::Month, date(2026, 11, 1), today),
+            Some("next month".to_string())
+        );
+        assert_eq!(relative_name(Period::Year, date(2024, 1, 1), today), None);
+        assert_eq!(
+            relative_name(Period::Day, date(2026, 10, 19), today),
+            Some("tomorrow".to_string())
+        );
+    }
+}
@@ -1,248 +1,245 @@
 use std::iter;
 use std::path::Path;
 
+use tower_lsp::jsonrpc::{Error, Result};
 use tower_lsp::lsp_types::{
     DocumentChangeOperation, DocumentChanges, OneOf, OptionalVersionedTextDocumentIdentifier,
-    RenameFile, RenameParams, ResourceOp, TextDocumentEdit, TextEdit, Url, WorkspaceEdit,
+    Position, PrepareRenameResponse, Range, RenameFile, RenameParams, ResourceOp, TextDocumentEdit,
+    TextDocumentPositionParams, TextEdit, Url, WorkspaceEdit,
 };
 
-use crate::vault::{MDHeading, Reference, Referenceable, Vault};
+use crate::{
+    block_ids::rename_block_id_edit,
+    link_format::{link_infile_range, link_path_range},
+    vault::{MDTag, MyRange, Reference, Referenceable, Vault},
+};
 
-pub fn rename(vault: &Vault, params: &RenameParams, path: &Path) -> Option<WorkspaceEdit> {
+/// The edit of a rename; an error if the new name can not be the name of what is renamed
+pub fn rename(vault: &Vault, params: &RenameParams, path: &Path) -> Result<Option<WorkspaceEdit>> {
     let position = params.text_document_position.position;
-    let referenceable = vault.select_referenceable_at_position(path, position)?;
+    let Some((referenceable, _)) = renamed_referenceable(vault, path, position) else {
+        return Ok(None);
+    };
 
-    let (referenceable_document_change, new_ref_name): (Option<DocumentChangeOperation>, String) =
-        match referenceable {
-            Referenceable::Heading(path, heading) => {
-                let new_text = format!("{} {}", "#".repeat(heading.level.0), params.new_name); // move this obsidian syntax specific stuff to the vault
+    check_new_name(&referenceable, &params.new_name)?;
 
-                let change_op = DocumentChangeOperation::Edit(TextDocumentEdit {
-                    text_document: tower_lsp::lsp_types::OptionalVersionedTextDocumentIdentifier {
-                        uri: Url::from_file_path(path).ok()?,
-                        version: None,
-                    },
-                    edits: vec![OneOf::Left(TextEdit {
-                        range: *heading.range,
-                        new_text,
-                    })],
-                });
+    Ok(rename_referenceable(vault, params, referenceable))
+}
 
-                // {path name}#{new name}
-                let name = format!("{}#{}", path.file_stem()?.to_string_lossy().to_owned(), params.new_name);
+/// An invalid params error if the new name can not be read back as the name of the referenceable
+fn check_new_name(referenceable: &Referenceable, new_name: &str) -> Result<()> {
+    match referenceable {
+        Referenceable::IndexedBlock(..) => {
+            let new_index = new_name.trim_start_matches('^');
+            let is_block_id = !new_index.is_empty()
+                && new_index
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || c == '-' || c == '_');
+            if !is_block_id {
+                return Err(Error::invalid_params(format!(
+                    "{new_name:?} is not a block id: block ids are letters, digits, '-' and '_'"
+                )));
+            }
 
-                (Some(change_op), name.to_string())
+            Ok(())
+        }
+        Referenceable::Footnote(..) => {
+            if !is_label(new_name.trim_start_matches('^')) {
+                return Err(Error::invalid_params(format!(
+                    "{new_name:?} is not a footnote label: labels have no whitespace, '[' or ']'"
+                )));
             }
-            Referenceable::File(path, _file) => {
-                let new_path = path.with_file_name(&params.new_name).with_extension("md");
 
-                let change_op = DocumentChangeOperation::Op(ResourceOp::Rename(RenameFile {
-                    old_uri: Url::from_file_path(path).ok()?,
-                    new_uri: Url::from_file_path(new_path.clone()).ok()?,
-                    options: None,
-                    annotation_id: None,
-                }));
+            Ok(())
+        }
+        Referenceable::LinkRefDef(..) => {
+            let new_label = new_name.trim_start_matches('[').trim_end_matches(']');
+            // link reference labels are read back with at least two characters
+            if !is_label(new_label) || new_label.starts_with('^') || new_label.chars().count() < 2 {
+                return Err(Error::invalid_params(format!(
+                    "{new_name:?} is not a link reference label: labels have at least two characters, do not start with '^' and have no whitespace, '[' or ']'"
+                )));
+            }
 
-                let name = params.new_name.clone();
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
 
-                (Some(change_op), name)
-            }
-            Referenceable::Tag(_path, _tag) => {
-                let new_ref_name = params.new_name.clone();
+fn rename_referenceable(
+    vault: &Vault,
+    params: &RenameParams,
+    referenceable: Referenceable,
+) -> Option<WorkspaceEdit> {
+    let position = params.text_document_position.position;
 
-                let _new_tag = format!("#{}", new_ref_name);
+    // for nested tags, only the level under the cursor and its descendants are renamed: #parent/chi|ld renames #parent/child
+    let tag_level;
+    let referenceable = match referenceable {
+        Referenceable::Tag(tag_path, tag) => {
+            tag_level = tag_level_at_position(vault, tag_path, tag, position);
+            Referenceable::Tag(tag_path, &tag_level)
+        }
+        referenceable => referenceable,
+    };
 
-                (None, new_ref_name)
-            }
-            _ => return None,
-        };
+    // only the renamed part of a link is rewritten, so that links keep the style that they are written in
+    let (referenceable_document_change, new_ref_name) = match referenceable {
+        Referenceable::Heading(path, heading) => {
+            let new_text = format!("{} {}", "#".repeat(heading.level.0), params.new_name); // move this obsidian syntax specific stuff to the vault
+
+            let change_op = DocumentChangeOperation::Edit(TextDocumentEdit {
+                text_document: OptionalVersionedTextDocumentIdentifier {
+                    uri: Url::from_file_path(path).ok()?,
+                    version: None,
+                },
+                edits: vec![OneOf::Left(TextEdit {
+                    range: *heading.range,
+                    new_text,
+                })],
+            });
+
+            (Some(change_op), params.new_name.clone())
+        }
+        Referenceable::File(path, _file) => {
+            let new_path = path.with_file_name(&params.new_name).with_extension("md");
+
+            let change_op = DocumentChangeOperation::Op(ResourceOp::Rename(RenameFile {
+                old_uri: Url::from_file_path(path).ok()?,
+                new_uri: Url::from_file_path(new_path.clone()).ok()?,
+                options: None,
+                annotation_id: None,
+            }));
+
+            (Some(change_op), params.new_name.clone())
+        }
+        Referenceable::Tag(_, tag) => {
+            let new_name = params.new_name.trim_start_matches('#');
+
+            // a name without '/' replaces only the renamed level; a name with '/' replaces the whole path
+            let new_ref_name = match tag.tag_ref.rsplit_once('/') {
+                Some((parent, _)) if !new_name.contains('/') => {
+                    format!("#{}/{}", parent, new_name)
+                }
+                _ => format!("#{}", new_name),
+            };
+
+            (None, new_ref_name)
+        }
+        Referenceable::IndexedBlock(path, block) => {
+            let new_index = params.new_name.trim_start_matches('^');
+
+            return rename_block_id_edit(vault, path, block, new_index);
+        }
+        Referenceable::Footnote(path, footnote) => {
+            let new_index = params.new_name.trim_start_matches('^');
+            let new_ref_name = format!("[^{}]", new_index);
+
+            let change_op = label_change(path, &footnote.range, &footnote.index, &new_ref_name)?;
+
+            (Some(change_op), new_ref_name)
+        }
+        Referenceable::LinkRefDef(path, link_ref) => {
+            let new_label = params
+                .new_name
+                .trim_start_matches('[')
+                .trim_end_matches(']');
+            let new_ref_name = format!("[{}]", new_label);
+
+            let change_op = label_change(
+                path,
+                &link_ref.range,
+                &link_ref.link_ref_name,
+                &new_ref_name,
+            )?;
+
+            (Some(change_op), new_ref_name)
+        }
+        _ => return None,
+    };
 
     let references = vault.select_references_for_referenceable(&referenceable)?;
 
     let references_changes = references
         .into_iter()
         .filter_map(|(path, reference)| {
-            // update references
+            let renamed_file = matches!(referenceable, Referenceable::File(..));
+            let renamed_heading = matches!(referenceable, Referenceable::Heading(..));
+            let renamed_footnote = matches!(referenceable, Referenceable::Footnote(..));
+            let renamed_link_ref = matches!(referenceable, Referenceable::LinkRefDef(..));
 
-            match reference {
-                // todo: move the obsidian link formatting to the vault module; it should be centralized there; no honestly this code sucks; this whole file
-                Reference::WikiFileLink(data)
-                    if matches!(referenceable, Referenceable::File(..)) =>
-                {
-                    let new_text = format!(
-                        "[[{}{}]]",
-                        new_ref_name,
-                        data.display_text
-                            .as_ref()
-                            .map(|text| format!("|{text}"))
-                            .unwrap_or_else(|| String::from(""))
-                    );
+            let line = || vault.select_line(path, reference.data().range.start.line as isize);
 
-                    Some(TextDocumentEdit {
-                        text_document:
-                            tower_lsp::lsp_types::OptionalVersionedTextDocumentIdentifier {
-                                uri: Url::from_file_path(path).ok()?,
-                                version: None,
-                            },
-                        edits: vec![OneOf::Left(TextEdit {
-                            range: *data.range,
-                            new_text,
-                        })],
-                    })
-                }
-                Reference::WikiHeadingLink(data, _file, infile)
-                | Reference::WikiIndexedBlockLink(data, _file, infile)
-                    if matches!(referenceable, Referenceable::File(..)) =>
+            let (range, new_text) = match reference {
+                Reference::WikiFileLink(..)
+                | Reference::WikiHeadingLink(..)
+                | Reference::WikiIndexedBlockLink(..)
+                | Reference::MDFileLink(..)
+                | Reference::MDHeadingLink(..)
+                | Reference::MDIndexedBlockLink(..)
+                    if renamed_file =>
                 {
-                    let new_text = format!(
-                        "[[{}#{}{}]]",
-                        new_ref_name,
-                        infile,
-                        data.display_text
-                            .as_ref()
-                            .map(|text| format!("|{text}"))
-                            .unwrap_or_else(|| String::from(""))
-                    );
+                    let line = line()?;
+                    let part = link_path_range(reference, &line)?;
+                    // links to a heading of the same file, `[[#Heading]]`, have no file part
+                    if part.is_empty() {
+                        return None;
+                    }
 
-                    Some(TextDocumentEdit {
-                        text_document:
-                            tower_lsp::lsp_types::OptionalVersionedTextDocumentIdentifier {
-                                uri: Url::from_file_path(path).ok()?,
-                                version: None,
-                            },
-                        edits: vec![OneOf::Left(TextEdit {
-                            range: *data.range,
-                            new_text,
-                        })],
-                    })
+                    // the file stays in its folder
+                    let new_path = match String::from_iter(&line[part.clone()]).rsplit_once('/') {
+                        Some((folder, _)) => format!("{}/{}", folder, new_ref_name),
+                        None => new_ref_name.clone(),
+                    };
+
+                    link_part_edit(reference, &line, part, &new_path)
                 }
-                Reference::WikiHeadingLink(data, _file, _heading)
-                    if matches!(referenceable, Referenceable::Heading(..)) =>
+                Reference::WikiHeadingLink(..) | Reference::MDHeadingLink(..)
+                    if renamed_heading =>
                 {
-                    let new_text = format!(
-                        "[[{}{}]]",
-                        new_ref_name,
-                        data.display_text
-                            .as_ref()
-                            .map(|text| format!("|{text}"))
-                            .unwrap_or_else(|| String::from(""))
-                    );
+                    let line = line()?;
+                    let part = link_infile_range(reference, &line)?;
 
-                    Some(TextDocumentEdit {
-                        text_document:
-                            tower_lsp::lsp_types::OptionalVersionedTextDocumentIdentifier {
-                                uri: Url::from_file_path(path).ok()?,
-                                version: None,
-                            },
-                        edits: vec![OneOf::Left(TextEdit {
-                            range: *data.range,
-                            new_text,
-                        })],
-                    })
+                    link_part_edit(reference, &line, part, &new_ref_name)
                 }
                 Reference::Tag(data) => {
-                    let new_text = format!(
-                        "#{}",
-                        data.reference_text.replacen(
-                            &*referenceable.get_refname(vault.root_dir())?,
-                            &new_ref_name,
-                            1
-                        )
+                    // the refname is a prefix of the reference for nested tags: #parent/child
+                    let new_tag = data.reference_text.replacen(
+                        &*referenceable.get_refname(vault.root_dir())?,
+                        &new_ref_name,
+                        1,
                     );
 
-                    Some(TextDocumentEdit {
-                        text_document: OptionalVersionedTextDocumentIdentifier {
-                            uri: Url::from_file_path(path).ok()?,
-                            version: None,
-                        },
-                        edits: vec![OneOf::Left(TextEdit {
-                            range: *data.range,
-                            new_text,
-                        })],
-                    })
-                }
-                Reference::MDFileLink(data) if matches!(referenceable, Referenceable::File(..)) => {
-                    let new_text = format!(
-                        "[{}]({})",
-                        data.display_text
-                            .as_ref()
-                            .map(|text| format!("|{text}"))
-                            .unwrap_or_else(|| String::from("")),
-                        new_ref_name,
-                    );
+                    // frontmatter tags are written without the '#'
+                    let new_tag = match starts_with_hash(vault, path, &data.range) {
+                        true => new_tag,
+                        false => new_tag.trim_start_matches('#').to_string(),
+                    };
 
-                    Some(TextDocumentEdit {
-                        text_document:
-                            tower_lsp::lsp_types::OptionalVersionedTextDocumentIdentifier {
-                                uri: Url::from_file_path(path).ok()?,
-                                version: None,
-                            },
-                        edits: vec![OneOf::Left(TextEdit {
-                            range: *data.range,
-                            new_text,
-                        })],
-                    })
+                    (*data.range, new_tag)
                 }
-
-                Reference::MDHeadingLink(data, _file, infile)
-                | Reference::MDIndexedBlockLink(data, _file, infile)
-                    if matches!(referenceable, Referenceable::File(..)) =>
-                {
-                    let new_text = format!(
-                        "[{}]({}#{})",
-                        data.display_text
-                            .as_ref()
-                            .map(|text| format!("|{text}"))
-                            .unwrap_or_else(|| String::from("")),
-                        new_ref_name,
-                        infile,
-                    );
-
-                    Some(TextDocumentEdit {
-                        text_document:
-                            tower_lsp::lsp_types::OptionalVersionedTextDocumentIdentifier {
-                                uri: Url::from_file_path(path).ok()?,
-                                version: None,
-                            },
-                        edits: vec![OneOf::Left(TextEdit {
-                            range: *data.range,
-                            new_text,
-                        })],
-                    })
+                Reference::Footnote(data) if renamed_footnote => {
+                    (*data.range, new_ref_name.clone())
                 }
-                Reference::WikiHeadingLink(data, _file, _heading)
-                    if matches!(referenceable, Referenceable::Heading(..)) =>
-                {
-                    let new_text = format!(
-                        "[{}]({})",
-                        data.display_text
-                            .as_ref()
-                            .map(|text| format!("|{text}"))
-                            .unwrap_or_else(|| String::from("")),
-                        new_ref_name,
-                    );
+                Reference::LinkRef(data) if renamed_link_ref => (*data.range, new_ref_name.clone()),
+                Reference::MDHeadingLink(..)
+                | Reference::MDIndexedBlockLink(..)
+                | Reference::WikiFileLink(..)
+                | Reference::WikiHeadingLink(..)
+                | Reference::WikiIndexedBlockLink(..)
+                | Reference::MDFileLink(..)
+                | Reference::Footnote(..)
+                | Reference::LinkRef(_) => return None,
+            };
 
-                    Some(TextDocumentEdit {
-                        text_document:
-                            tower_lsp::lsp_types::OptionalVersionedTextDocumentIdentifier {
-                                uri: Url::from_file_path(path).ok()?,
-                                version: None,
-                            },
-                        edits: vec![OneOf::Left(TextEdit {
-                            range: *data.range,
-                            new_text,
-                        })],
-                    })
-                }
-                Reference::MDHeadingLink(_, _, _) => None,
-                Reference::MDIndexedBlockLink(_, _, _) => None,
-                Reference::WikiFileLink(..) => None,
-                Reference::WikiHeadingLink(..) => None,
-                Reference::WikiIndexedBlockLink(..) => None,
-                Reference::MDFileLink(..) => None,
-                Reference::Footnote(..) => None,
-                Reference::LinkRef(_) => None,
-            }
+            Some(TextDocumentEdit {
+                text_document: OptionalVersionedTextDocumentIdentifier {
+                    uri: Url::from_file_path(path).ok()?,
+                    version: None,
+                },
+                edits: vec![OneOf::Left(TextEdit { range, new_text })],
+            })
         })
         .map(DocumentChangeOperation::Edit);
 
@@ -255,3 +252,412 @@ pub fn rename(vault: &Vault, params: &RenameParams, path: &Path) -> Option<Works
         ..Default::default()
     })
 }
+
+/// What a rename would rename, with the range and the current name to show in the editor; None if the position
+/// cannot be renamed
+pub fn prepare_rename(
+    vault: &Vault,
+    params: &TextDocumentPositionParams,
+    path: &Path,
+) -> Option<PrepareRenameResponse> {
+    let position = params.position;
+    let (referenceable, link) = renamed_referenceable(vault, path, position)?;
+    let link_range = link.map(|link| *link.data().range);
+    let line = vault.select_line(path, position.line as isize)?;
+    let line_range = |range: std::ops::Range<usize>| Range {
+        start: Position {
+            line: position.line,
+            character: range.start as u32,
+        },
+        end: Position {
+            line: position.line,
+            character: range.end as u32,
+        },
+    };
+
+    let (range, placeholder) = match referenceable {
+        Referenceable::Heading(_, heading) => (*heading.range, heading.heading_text.clone()),
+        Referenceable::File(file_path, _) => {
+            let file_stem = file_path.file_stem()?.to_str()?.to_string();
+
+            // the file name in the link to the file; in the file itself, nothing in the text is the name of the file, so
+            // the range is empty at the position
+            let range = match link {
+                Some(link) => {
+                    let part = link_path_range(link, &line)?;
+                    let file_name_start = line[part.clone()]
+                        .iter()
+                        .rposition(|c| *c == '/')
+                        .map_or(part.start, |slash| part.start + slash + 1);
+                    if file_name_start == part.end {
+                        return None;
+                    }
+
+                    line_range(file_name_start..part.end)
+                }
+                None => Range {
+                    start: position,
+                    end: position,
+                },
+            };
+
+            (range, file_stem)
+        }
+        Referenceable::Tag(tag_path, tag) => {
+            let tag_level = tag_level_at_position(vault, tag_path, tag, position);
+            let level = tag_level.tag_ref.rsplit('/').next()?.to_string();
+
+            (*tag.range, level)
+        }
+        Referenceable::IndexedBlock(_, block) => {
+            (link_range.unwrap_or(*block.range), block.index.clone())
+        }
+        Referenceable::Footnote(_, footnote) => (
+            link_range.unwrap_or(*label_range(&footnote.range, &footnote.index)),
+            footnote.index.trim_start_matches('^').to_string(),
+        ),
+        Referenceable::LinkRefDef(_, link_ref) => (
+            link_range.unwrap_or(*label_range(&link_ref.range, &link_ref.link_ref_name)),
+            link_ref.link_ref_name.clone(),
+        ),
+        _ => return None,
+    };
+
+    Some(PrepareRenameResponse::RangeWithPlaceholder { range, placeholder })
+}
+
+/// The referenceable at the position, or the file, block, footnote or link reference definition that the link at the position
+/// links to, with the link
+fn renamed_referenceable<'a>(
+    vault: &'a Vault,
+    path: &'a Path,
+    position: Position,
+) -> Option<(Referenceable<'a>, Option<&'a Reference>)> {
+    let link = vault
+        .select_reference_at_position(path, position)
+        .filter(|reference| {
+            matches!(
+                reference,
+                Reference::WikiFileLink(..)
+                    | Reference::MDFileLink(..)
+                    | Reference::WikiIndexedBlockLink(..)
+                    | Reference::MDIndexedBlockLink(..)
+                    | Reference::Footnote(..)
+                    | Reference::LinkRef(..)
+            )
+        });
+
+    match link {
+        // an unresolved link has nothing to rename
+        Some(link) => {
+            let referenceable = vault
+                .select_referenceables_for_reference(link, path)
+                .into_iter()
+                .find(|referenceable| !referenceable.is_unresolved())?;
+
+            Some((referenceable, Some(link)))
+        }
+        None => Some((
+            vault.select_referenceable_at_position(path, position)?,
+            None,
+        )),
+    }
+}
+
+/// Replace the part of the link in the range of its line; spaces are encoded as `%20` in markdown links that are not
+/// between `<>`
+fn link_part_edit(
+    reference: &Reference,
+    line: &[char],
+    part: std::ops::Range<usize>,
+    new_part: &str,
+) -> (Range, String) {
+    let in_angle_brackets = line.get(part.start.saturating_sub(1)) == Some(&'<');
+    let new_text = match reference {
+        Reference::MDFileLink(..)
+        | Reference::MDHeadingLink(..)
+        | Reference::MDIndexedBlockLink(..)
+            if !in_angle_brackets =>
+        {
+            new_part.replace(' ', "%20")
+        }
+        _ => new_part.to_string(),
+    };
+
+    let line_nr = reference.data().range.start.line;
+    let range = Range {
+        start: Position {
+            line: line_nr,
+            character: part.start as u32,
+        },
+        end: Position {
+            line: line_nr,
+            character: part.end as u32,
+        },
+    };
+
+    (range, new_text)
+}
+
+/// Footnote indexes and link reference labels are used between brackets, so they cannot have brackets or whitespace
+fn is_label(label: &str) -> bool {
+    !label.is_empty() && !label.contains(|c: char| c.is_whitespace() || c == '[' || c == ']')
+}
+
+/// The range of `[label]` at the start of the definition of a footnote or link reference
+fn label_range(definition_range: &MyRange, label: &str) -> MyRange {
+    let start = definition_range.start;
+
+    MyRange(Range {
+        start,
+        end: Position {
+            line: start.line,
+            character: start.character + label.chars().count() as u32 + 2,
+        },
+    })
+}
+
+/// Replace the `[label]` of the definition of a footnote or link reference
+fn label_change(
+    path: &Path,
+    definition_range: &MyRange,
+    label: &str,
+    new_text: &str,
+) -> Option<DocumentChangeOperation> {
+    Some(DocumentChangeOperation::Edit(TextDocumentEdit {
+        text_document: OptionalVersionedTextDocumentIdentifier {
+            uri: Url::from_file_path(path).ok()?,
+            version: None,
+        },
+        edits: vec![OneOf::Left(TextEdit {
+            range: *label_range(definition_range, label),
+            new_text: new_text.to_string(),
+        })],
+    }))
+}
+
+fn starts_with_hash(vault: &Vault, path: &Path, range: &MyRange) -> bool {
+    vault
+        .select_line(path, range.start.line as isize)
+        .is_some_and(|line| line.get(range.start.character as usize) == Some(&'#'))
+}
+
+/// The tag up to the end of the level that the position is in
+fn tag_level_at_position(vault: &Vault, path: &Path, tag: &MDTag, position: Position) -> MDTag {
+    let hash_len = if starts_with_hash(vault, path, &tag.range) {
+        1
+    } else {
+        0
+    };
+    let offset = (position.character.saturating_sub(tag.range.start.character) as usize)
+        .saturating_sub(hash_len)
+        .min(tag.tag_ref.len());
+
+    let level_end = tag.tag_ref[offset..]
+        .find('/')
+        .map(|i| offset + i)
+        .unwrap_or(tag.tag_ref.len());
+
+    MDTag {
+        tag_ref: tag.tag_ref[..level_end].to_string(),
+        range: tag.range,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use tower_lsp::lsp_types::{
+        DocumentChangeOperation, DocumentChanges, OneOf, Position, PrepareRenameResponse,
+        RenameParams, TextDocumentIdentifier, TextDocumentPositionParams, Url, WorkspaceEdit,
+    };
+
+    use crate::{config::Settings, vault::Vault};
+
+    use super::{prepare_rename, rename};
+
+    /// The text edits of a rename as (file name, line, new text), in order
+    fn text_edits(edit: WorkspaceEdit) -> Vec<(String, u32, String)> {
+        let Some(DocumentChanges::Operations(operations)) = edit.document_changes else {
+            panic!("the rename should edit documents");
+        };
+
+        let mut edits = operations
+            .into_iter()
+            .flat_map(|operation| match operation {
+                DocumentChangeOperation::Edit(edit) => {
+                    let path = edit.text_document.uri.to_file_path().unwrap();
+                    let file_name = path.file_name().unwrap().to_string_lossy().to_string();
+
+                    edit.edits
+                        .into_iter()
+                        .flat_map(|edit| match edit {
+                            OneOf::Left(edit) => {
+                                Some((file_name.clone(), edit.range.start.line, edit.new_text))
+                            }
+                            OneOf::Right(_) => None,
+                        })
+                        .collect()
+                }
+                DocumentChangeOperation::Op(_) => vec![],
+            })
+            .collect::<Vec<_>>();
+        edits.sort();
+
+        edits
+    }
+
+    #[test]
+    fn renames_keep_link_style() {
+        let root_dir = Path::new("/vault");
+        let settings = Settings::for_vault(root_dir);
+        let vault = Vault::from_texts(
+            &settings,
+            root_dir,
+            &[
+                ("meeting.md", "# Agenda\n\nSee [[#Agenda]]\n"),
+                (
+                    "todo.md",
+                    "- [[meeting]]\n- [[meeting#Agenda|the agenda]]\n- [notes](meeting.md#Agenda)\n",
+                ),
+            ],
+        );
+        let path = root_dir.join("meeting.md");
+        let rename_at = |line, new_name: &str| {
+            let params = RenameParams {
+                text_document_position: TextDocumentPositionParams {
+                    text_document: TextDocumentIdentifier {
+                        uri: Url::from_file_path(&path).unwrap(),
+                    },
+                    position: Position { line, character: 3 },
+                },
+                new_name: new_name.to_string(),
+                work_done_progress_params: Default::default(),
+            };
+
+            text_edits(rename(&vault, &params, &path).unwrap().unwrap())
+        };
+
+        let edit = |file: &str, line, text: &str| (file.to_string(), line, text.to_string());
+
+        assert_eq!(
+            rename_at(0, "Next Steps"),
+            vec![
+                edit("meeting.md", 0, "# Next Steps"),
+                edit("meeting.md", 2, "Next Steps"),
+                edit("todo.md", 1, "Next Steps"),
+                edit("todo.md", 2, "Next%20Steps"),
+            ]
+        );
+        // the link to a heading of the same file has no file part to rename
+        assert_eq!(
+            rename_at(1, "standup"),
+            vec![
+                edit("todo.md", 0, "standup"),
+                edit("todo.md", 1, "standup"),
+                edit("todo.md", 2, "standup"),
+            ]
+        );
+    }
+
+    #[test]
+    fn renames_footnotes_and_link_refs() {
+        let root_dir = Path::new("/vault");
+        let settings = Settings::for_vault(root_dir);
+        let vault = Vault::from_texts(
+            &settings,
+            root_dir,
+            &[
+                (
+                    "claims.md",
+                    "# Claims\n\n- Some claim[^1]\n- Another claim[^1]\n\nSee [[notes/other]] and [the docs][docs]\n\n[^1]: The source\n[docs]: https://example.com\n[[missing]]\n",
+                ),
+                ("notes/other.md", "Other\n"),
+            ],
+        );
+        let path = root_dir.join("claims.md");
+        let position = |line, character| TextDocumentPositionParams {
+            text_document: TextDocumentIdentifier {
+                uri: Url::from_file_path(&path).unwrap(),
+            },
+            position: Position { line, character },
+        };
+        let prepare_at = |line, character| {
+            let Some(PrepareRenameResponse::RangeWithPlaceholder { range, placeholder }) =
+                prepare_rename(&vault, &position(line, character), &path)
+            else {
+                panic!("the position should be renameable");
+            };
+            (range.start.character, range.end.character, placeholder)
+        };
+        let rename_at = |line, character, new_name: &str| {
+            let params = RenameParams {
+                text_document_position: position(line, character),
+                new_name: new_name.to_string(),
+                work_done_progress_params: Default::default(),
+            };
+
+            rename(&vault, &params, &path).map(|edit| edit.map(text_edits))
+        };
+
+        let edit = |line, text: &str| ("claims.md".to_string(), line, text.to_string());
+
+        // on the use of [^1] in `- Some claim[^1]`
+        assert_eq!(prepare_at(2, 13), (12, 16, "1".to_string()));
+        assert_eq!(
+            rename_at(2, 13, "claim"),
+            Ok(Some(vec![
+                edit(2, "[^claim]"),
+                edit(3, "[^claim]"),
+                edit(7, "[^claim]"),
+            ]))
+        );
+        assert!(rename_at(2, 13, "a claim").is_err());
+
+        // the file name of the link to a file
+        assert_eq!(prepare_at(5, 8), (12, 17, "other".to_string()));
+        // outside of links, the file itself is renamed, with its name as the placeholder
+        assert_eq!(prepare_at(2, 4), (4, 4, "claims".to_string()));
+
+        // a link reference label has at least two characters
+        assert!(rename_at(5, 36, "d").is_err());
+        assert_eq!(
+            rename_at(5, 36, "guide"),
+            Ok(Some(vec![edit(5, "[guide]"), edit(8, "[guide]")]))
+        );
+
+        // an unresolved link has nothing to rename
+        assert!(prepare_rename(&vault, &position(9, 3), &path).is_none());
+    }
+
+    #[test]
+    fn rejects_invalid_new_names() {
+        let root_dir = Path::new("/vault");
+        let settings = Settings::for_vault(root_dir);
+        let vault = Vault::from_texts(
+            &settings,
+            root_dir,
+            &[("note.md", "A paragraph ^abc\n\nSee [[#^abc]]\n")],
+        );
+        let path = root_dir.join("note.md");
+        let rename_at = |line, character, new_name: &str| {
+            let params = RenameParams {
+                text_document_position: TextDocumentPositionParams {
+                    text_document: TextDocumentIdentifier {
+                        uri: Url::from_file_path(&path).unwrap(),
+                    },
+                    position: Position { line, character },
+                },
+                new_name: new_name.to_string(),
+                work_done_progress_params: Default::default(),
+            };
+
+            rename(&vault, &params, &path)
+        };
+
+        assert!(rename_at(2, 7, "^new-id").unwrap().is_some());
+        assert!(rename_at(2, 7, "new id").is_err());
+        assert!(rename_at(2, 7, "^").is_err());
+    }
+}
@@ -0,0 +1,241 @@
+use std::cmp::Reverse;
+
+use itertools::Itertools;
+use nucleo_matcher::{
+    pattern::{AtomKind, CaseMatching, Normalization, Pattern},
+    Matcher, Utf32Str,
+};
+use pathdiff::diff_paths;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use tower_lsp::lsp_types::{Location, Position, Range, SymbolInformation, SymbolKind, Url};
+
+use crate::{
+    config::{Case, Settings},
+    vault::Vault,
+};
+
+/// Arguments of the `search` command
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct SearchQuery {
+    /// Words that must all appear in a line, in any order
+    pub query: String,
+    /// Only search the files in this folder, relative to the vault root
+    pub folder: Option<String>,
+    /// The maximum number of results; `DEFAULT_LIMIT` if not set
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchResult {
+    /// relative to the vault root
+    pub path: String,
+    pub uri: Url,
+    /// The span of the line from the first to the last matched character
+    pub range: Range,
+    /// The line, trimmed
+    pub text: String,
+    /// The heading of the section that the line is in
+    pub heading: Option<String>,
+    pub score: u32,
+}
+
+const DEFAULT_LIMIT: usize = 100;
+
+/// Matches in headings rank higher; a matched character is worth about 16
+const HEADING_BONUS: u32 = 32;
+
+/// Lines of the notes that contain every word of the query, best matches first: matches at word boundaries
+/// and in headings rank higher. Ties are ordered by file and line
+pub fn search(vault: &Vault, settings: &Settings, query: &SearchQuery) -> Vec<SearchResult> {
+    let pattern = Pattern::new(
+        &query.query,
+        match settings.case_matching {
+            Case::Smart => CaseMatching::Smart,
+            Case::Ignore => CaseMatching::Ignore,
+            Case::Respect => CaseMatching::Respect,
+        },
+        Normalization::Smart,
+        AtomKind::Substring,
+    );
+    if pattern.atoms.is_empty() {
+        return vec![];
+    }
+
+    let root_dir = vault.root_dir();
+    let folder = query
+        .folder
+        .as_ref()
+        .map(|folder| root_dir.join(folder.trim_matches('/')));
+
+    vault
+        .ropes
+        .par_iter()
+        .filter(|(path, _)| match &folder {
+            Some(folder) => path.starts_with(folder),
+            None => true,
+        })
+        .map_init(
+            || {
+                (
+                    Matcher::new(nucleo_matcher::Config::DEFAULT),
+                    Vec::new(),
+                    Vec::new(),
+                )
+            },
+            |(matcher, buf, indices), (path, rope)| {
+                let headings = vault
+                    .md_files
+                    .get(path)
+                    .map(|md_file| md_file.headings.as_slice())
+                    .unwrap_or_default();
+                let (Some(relative), Ok(uri)) =
+                    (diff_paths(path, root_dir), Url::from_file_path(path))
+                else {
+                    return vec![];
+                };
+                let relative = relative.to_string_lossy().replace('\\', "/");
+
+                rope.lines()
+                    .enumerate()
+                    .flat_map(|(line_nr, line)| {
+                        let line = line.to_string();
+                        let line = line.trim_end_matches(['\n', '\r']);
+
+                        indices.clear();
+                        let score = pattern.indices(Utf32Str::new(line, buf), matcher, indices)?;
+                        let start = *indices.iter().min()?;
+                        let end = *indices.iter().max()? + 1;
+
+                        let line_nr = line_nr as u32;
+                        let section = headings
+                            .iter()
+                            .filter(|heading| heading.range.start.line <= line_nr)
+                            .max_by_key(|heading| heading.range.start.line);
+                        let in_heading =
+                            section.is_some_and(|heading| heading.range.start.line == line_nr);
+
+                        Some(SearchResult {
+                            path: relative.clone(),
+                            uri: uri.clone(),
+                            range: Range {
+                                start: Position {
+                                    line: line_nr,
+                                    character: start,
+                                },
+                                end: Position {
+                                    line: line_nr,
+                                    character: end,
+                                },
+                            },
+                            text: line.trim().to_string(),
+                            heading: section.map(|heading| heading.heading_text.clone()),
+                            score: match in_heading {
+                                true => score + HEADING_BONUS,
+                                false => score,
+                            },
+                        })
+                    })
+                    .collect::<Vec<_>>()
+            },
+        )
+        .flatten()
+        .collect::<Vec<_>>()
+        .into_iter()
+        .sorted_by(|a, b| {
+            (Reverse(a.score), &a.path, a.range.start.line).cmp(&(
+                Reverse(b.score),
+                &b.path,
+                b.range.start.line,
+            ))
+        })
+        .take(query.limit.unwrap_or(DEFAULT_LIMIT))
+        .collect()
+}
+
+/// Workspace symbols for a search: `/query` searches the contents of the notes instead of their symbols
+#[allow(deprecated)]
+pub fn search_symbols(vault: &Vault, settings: &Settings, query: &str) -> Vec<SymbolInformation> {
+    let query = SearchQuery {
+        query: query.to_string(),
+        ..Default::default()
+    };
+
+    search(vault, settings, &query)
+        .into_iter()
+        .map(|result| SymbolInformation {
+            name: result.text,
+            kind: SymbolKind::STRING,
+            location: Location {
+                uri: result.uri,
+                range: result.range,
+            },
+            container_name: Some(match result.heading {
+                Some(heading) => format!("{} > {}", result.path, heading),
+                None => result.path,
+            }),
+            tags: None,
+            deprecated: None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::{config::Settings, vault::Vault};
+
+    use super::{search, SearchQuery};
+
+    #[test]
+    fn searches_note_contents() {
+        let root_dir = Path::new("/vault");
+        let settings = Settings::for_vault(root_dir);
+        let vault = Vault::from_texts(
+            &settings,
+            root_dir,
+            &[
+                (
+                    "a.md",
+                    "# Test Heading\n\nA heading for the test\nNo match here\n",
+                ),
+                ("notes/b.md", "# Other\n\nThe test of a heading\n"),
+            ],
+        );
+        let query = |query: &str, folder: Option<&str>, limit| SearchQuery {
+            query: query.to_string(),
+            folder: folder.map(String::from),
+            limit,
+        };
+
+        let results = search(&vault, &settings, &query("test heading", None, None));
+
+        assert_eq!(
+            results
+                .iter()
+                .map(|result| (result.path.as_str(), result.range.start.line))
+                .collect::<Vec<_>>(),
+            vec![("a.md", 0), ("a.md", 2), ("notes/b.md", 2)]
+        );
+        assert!(results
+            .windows(2)
+            .all(|pair| pair[0].score >= pair[1].score));
+        assert_eq!(results[0].heading.as_deref(), Some("Test Heading"));
+
+        let results = search(
+            &vault,
+            &settings,
+            &query("test heading", Some("notes"), None),
+        );
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].heading.as_deref(), Some("Other"));
+
+        let results = search(&vault, &settings, &query("heading", None, Some(2)));
+        assert_eq!(results.len(), 2);
+
+        assert!(search(&vault, &settings, &query("zzqx no such text", None, None)).is_empty());
+    }
+}
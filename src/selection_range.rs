@@ -0,0 +1,394 @@
+use std::path::Path;
+
+use itertools::Itertools;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use ropey::Rope;
+use tower_lsp::lsp_types::{Position, Range, SelectionRange, SelectionRangeParams};
+
+use crate::vault::{MDFile, Rangeable, Reference, Vault};
+
+pub fn selection_range(
+    vault: &Vault,
+    params: &SelectionRangeParams,
+    path: &Path,
+) -> Option<Vec<SelectionRange>> {
+    let rope = vault.ropes.get(path)?;
+    let md_file = vault.md_files.get(path)?;
+
+    Some(
+        params
+            .positions
+            .iter()
+            .map(|position| {
+                let ranges = selection_ranges(rope, md_file, *position);
+
+                // the spec requires a selection range for every position
+                ranges
+                    .into_iter()
+                    .rev()
+                    .fold(None, |parent, range| {
+                        Some(SelectionRange {
+                            range,
+                            parent: parent.map(Box::new),
+                        })
+                    })
+                    .unwrap_or(SelectionRange {
+                        range: Range {
+                            start: *position,
+                            end: *position,
+                        },
+                        parent: None,
+                    })
+            })
+            .collect(),
+    )
+}
+
+/// Structural ranges around the position, innermost first: link text, link, sentence or list item, paragraph or block,
+/// heading sections from the deepest to the top level and finally the whole document.
+/// Every range strictly contains the one before it.
+fn selection_ranges(rope: &Rope, md_file: &MDFile, position: Position) -> Vec<Range> {
+    let line = position.line as usize;
+    if line >= rope.len_lines() {
+        return vec![];
+    }
+
+    let line_chars = line_chars(rope, line);
+
+    let reference = md_file
+        .references
+        .iter()
+        .find(|reference| reference.includes_position(position));
+
+    let reference_ranges = reference
+        .into_iter()
+        .flat_map(|reference| {
+            [
+                link_text_range(reference, &line_chars),
+                Some(**reference.range()),
+            ]
+        })
+        .flatten();
+
+    let in_code_block = md_file
+        .codeblocks
+        .iter()
+        .find(|block| {
+            block.includes_position(position) && block.range().start.line != block.range().end.line
+        })
+        .map(|block| **block.range());
+
+    let item_or_sentence = match in_code_block {
+        Some(_) => None,
+        None => list_item_range(rope, line).or_else(|| sentence_range(&line_chars, position)),
+    };
+
+    let block = in_code_block.or_else(|| paragraph_range(rope, md_file, line));
+
+    let sections = md_file
+        .headings
+        .iter()
+        .enumerate()
+        .filter(|(_, heading)| heading.range.start.line <= position.line)
+        .flat_map(|(i, heading)| {
+            let section_end = md_file.headings[i + 1..]
+                .iter()
+                .find(|next| next.level <= heading.level)
+                .map(|next| next.range.start.line as usize)
+                .unwrap_or(rope.len_lines())
+                .checked_sub(1)?;
+
+            (line <= section_end).then(|| Range {
+                start: Position {
+                    line: heading.range.start.line,
+                    character: 0,
+                },
+                end: end_of_line(rope, section_end),
+            })
+        })
+        .sorted_by_key(|range| std::cmp::Reverse(range.start.line));
+
+    let document = Range {
+        start: Position {
+            line: 0,
+            character: 0,
+        },
+        end: end_of_line(rope, rope.len_lines().saturating_sub(1)),
+    };
+
+    reference_ranges
+        .chain(item_or_sentence)
+        .chain(block)
+        .chain(sections)
+        .chain(std::iter::once(document))
+        .fold(vec![], |mut acc: Vec<Range>, range| {
+            match acc.last() {
+                Some(last) if *last == range || !contains(&range, last) => (),
+                _ => acc.push(range),
+            }
+            acc
+        })
+}
+
+/// The text inside of the link: the display text if there is one, else the link target.
+fn link_text_range(reference: &Reference, line_chars: &[char]) -> Option<Range> {
+    let range = reference.range();
+    if range.start.line != range.end.line {
+        return None;
+    }
+
+    let (start, end) = (range.start.character as usize, range.end.character as usize);
+    let link = line_chars.get(start..end)?;
+
+    let (inner_start, inner_end) = match reference {
+        Reference::WikiFileLink(..)
+        | Reference::WikiHeadingLink(..)
+        | Reference::WikiIndexedBlockLink(..) => {
+            let (inner_start, inner_end) = (start + 2, end.checked_sub(2)?);
+
+            match link.iter().position(|c| *c == '|') {
+                Some(bar) => (start + bar + 1, inner_end),
+                None => (inner_start, inner_end),
+            }
+        }
+        Reference::MDFileLink(..)
+        | Reference::MDHeadingLink(..)
+        | Reference::MDIndexedBlockLink(..) => {
+            let close = link
+                .iter()
+                .tuple_windows()
+                .position(|(a, b)| *a == ']' && *b == '(')?;
+
+            (start + 1, start + close)
+        }
+        Reference::Tag(..) => (start + 1, end),
+        Reference::Footnote(..) | Reference::LinkRef(..) => (start + 1, end.checked_sub(1)?),
+    };
+
+    (inner_start < inner_end).then_some(Range {
+        start: Position {
+            line: range.start.line,
+            character: inner_start as u32,
+        },
+        end: Position {
+            line: range.start.line,
+            character: inner_end as u32,
+        },
+    })
+}
+
+static LIST_ITEM_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(?<indent>\s*)([-*+]|\d+[.)])\s").unwrap());
+
+/// A list item with its nested children
+fn list_item_range(rope: &Rope, line: usize) -> Option<Range> {
+    let text = String::from_iter(line_chars(rope, line));
+    let captures = LIST_ITEM_RE.captures(&text)?;
+    let indent = captures.name("indent")?.as_str().chars().count();
+
+    let last_line = (line + 1..rope.len_lines())
+        .take_while(|ln| {
+            let chars = line_chars(rope, *ln);
+            let child_indent = chars.iter().take_while(|c| c.is_whitespace()).count();
+            !chars.iter().all(|c| c.is_whitespace()) && child_indent > indent
+        })
+        .last()
+        .unwrap_or(line);
+
+    Some(Range {
+        start: Position {
+            line: line as u32,
+            character: indent as u32,
+        },
+        end: end_of_line(rope, last_line),
+    })
+}
+
+/// The sentence around the position, within the line
+fn sentence_range(line_chars: &[char], position: Position) -> Option<Range> {
+    let cursor = (position.character as usize).min(line_chars.len());
+    let is_end = |chars: &[char], i: usize| {
+        matches!(chars.get(i), Some('.' | '!' | '?'))
+            && !matches!(chars.get(i + 1), Some(c) if !c.is_whitespace())
+    };
+
+    let start = (0..cursor)
+        .rev()
+        .find(|i| is_end(line_chars, *i))
+        .map(|i| i + 1)
+        .unwrap_or(0);
+    let start = start
+        + line_chars[start..]
+            .iter()
+            .take_while(|c| c.is_whitespace())
+            .count();
+
+    let end = (cursor..line_chars.len())
+        .find(|i| is_end(line_chars, *i))
+        .map(|i| i + 1)
+        .unwrap_or(line_chars.len());
+
+    (start < end).then_some(Range {
+        start: Position {
+            line: position.line,
+            character: start as u32,
+        },
+        end: Position {
+            line: position.line,
+            character: end as u32,
+        },
+    })
+}
+
+/// Contiguous non-blank lines around the line; headings are blocks of their own.
+fn paragraph_range(rope: &Rope, md_file: &MDFile, line: usize) -> Option<Range> {
+    let is_blank = |ln: usize| line_chars(rope, ln).iter().all(|c| c.is_whitespace());
+    let is_heading = |ln: usize| {
+        md_file
+            .headings
+            .iter()
+            .any(|heading| heading.range.start.line as usize == ln)
+    };
+
+    if is_blank(line) {
+        return None;
+    }
+
+    if is_heading(line) {
+        return Some(Range {
+            start: Position {
+                line: line as u32,
+                character: 0,
+            },
+            end: end_of_line(rope, line),
+        });
+    }
+
+    let first = (0..line)
+        .rev()
+        .take_while(|ln| !is_blank(*ln) && !is_heading(*ln))
+        .last()
+        .unwrap_or(line);
+    let last = (line + 1..rope.len_lines())
+        .take_while(|ln| !is_blank(*ln) && !is_heading(*ln))
+        .last()
+        .unwrap_or(line);
+
+    Some(Range {
+        start: Position {
+            line: first as u32,
+            character: 0,
+        },
+        end: end_of_line(rope, last),
+    })
+}
+
+fn line_chars(rope: &Rope, line: usize) -> Vec<char> {
+    rope.get_line(line)
+        .map(|slice| {
+            slice
+                .chars()
+                .filter(|c| *c != '\n' && *c != '\r')
+                .collect_vec()
+        })
+        .unwrap_or_default()
+}
+
+fn end_of_line(rope: &Rope, line: usize) -> Position {
+    Position {
+        line: line as u32,
+        character: line_chars(rope, line).len() as u32,
+    }
+}
+
+fn contains(outer: &Range, inner: &Range) -> bool {
+    (outer.start.line, outer.start.character) <= (inner.start.line, inner.start.character)
+        && (outer.end.line, outer.end.character) >= (inner.end.line, inner.end.character)
+}
+
+#[cfg(test)]
+mod tests {
+    use ropey::Rope;
+    use tower_lsp::lsp_types::{Position, Range};
+
+    use crate::vault::{HeadingLevel, MDFile, MDHeading, MyRange, Reference, ReferenceData};
+
+    fn range(start: (u32, u32), end: (u32, u32)) -> Range {
+        Range {
+            start: Position {
+                line: start.0,
+                character: start.1,
+            },
+            end: Position {
+                line: end.0,
+                character: end.1,
+            },
+        }
+    }
+
+    #[test]
+    fn expands_from_link_to_document() {
+        let text = "# Top\n\n## Section\n\nFirst sentence. A [[link|display]] here.\nSecond line\n";
+        let rope = Rope::from_str(text);
+
+        let md_file = MDFile {
+            headings: vec![
+                MDHeading {
+                    heading_text: "Top".into(),
+                    range: MyRange(range((0, 0), (0, 5))),
+                    level: HeadingLevel(1),
+                },
+                MDHeading {
+                    heading_text: "Section".into(),
+                    range: MyRange(range((2, 0), (2, 10))),
+                    level: HeadingLevel(2),
+                },
+            ],
+            references: vec![Reference::WikiFileLink(ReferenceData {
+                reference_text: "link".into(),
+                display_text: Some("display".into()),
+                range: MyRange(range((4, 18), (4, 34))),
+            })],
+            ..Default::default()
+        };
+
+        let ranges = super::selection_ranges(
+            &rope,
+            &md_file,
+            Position {
+                line: 4,
+                character: 28,
+            },
+        );
+
+        assert_eq!(
+            ranges,
+            vec![
+                range((4, 25), (4, 32)),
+                range((4, 18), (4, 34)),
+                range((4, 16), (4, 40)),
+                range((4, 0), (5, 11)),
+                range((2, 0), (6, 0)),
+                range((0, 0), (6, 0)),
+            ]
+        )
+    }
+
+    #[test]
+    fn list_item_includes_children() {
+        let text = "- parent\n  - child\n- sibling";
+        let rope = Rope::from_str(text);
+
+        let ranges = super::selection_ranges(
+            &rope,
+            &MDFile::default(),
+            Position {
+                line: 0,
+                character: 3,
+            },
+        );
+
+        assert_eq!(ranges, vec![range((0, 0), (1, 9)), range((0, 0), (2, 9)),])
+    }
+}
@@ -6,12 +6,13 @@ use nucleo_matcher::{
     Matcher,
 };
 use tower_lsp::lsp_types::{
-    DocumentSymbol, DocumentSymbolParams, DocumentSymbolResponse, Location, SymbolInformation,
-    SymbolKind, Url, WorkspaceSymbolParams,
+    DocumentSymbol, DocumentSymbolParams, DocumentSymbolResponse, Location, Range,
+    SymbolInformation, SymbolKind, Url, WorkspaceSymbolParams,
 };
 
-use crate::vault::{MDHeading, Referenceable, Vault};
+use crate::vault::{MDHeading, MDTag, Referenceable, Vault};
 
+#[allow(deprecated)]
 pub fn workspace_symbol(
     vault: &Vault,
     _params: &WorkspaceSymbolParams,
@@ -55,7 +56,13 @@ pub fn workspace_symbol(
                     uri: Url::from_file_path(referenceable.get_path()).ok()?,
                     range,
                 },
-                container_name: None,
+                container_name: match referenceable {
+                    Referenceable::Tag(_, tag) => tag
+                        .tag_ref
+                        .rsplit_once('/')
+                        .map(|(parent, _)| format!("#{}", parent)),
+                    _ => None,
+                },
                 tags: None,
                 deprecated: None,
             })
@@ -85,11 +92,77 @@ pub fn document_symbol(
     path: &Path,
 ) -> Option<DocumentSymbolResponse> {
     let headings = vault.select_headings(path)?;
+    let tags = &vault.md_files.get(path)?.tags;
 
-    let tree = construct_tree(headings)?;
-    let lsp = map_to_lsp_tree(tree);
+    let headings_lsp = construct_tree(headings).map(map_to_lsp_tree);
+    let tags_lsp = construct_tag_tree(None, tags);
 
-    Some(DocumentSymbolResponse::Nested(lsp))
+    match (headings_lsp, tags_lsp) {
+        (None, tags_lsp) if tags_lsp.is_empty() => None,
+        (headings_lsp, tags_lsp) => Some(DocumentSymbolResponse::Nested(
+            headings_lsp.into_iter().flatten().chain(tags_lsp).collect(),
+        )),
+    }
+}
+
+/// The tags in the file as a hierarchy: #parent/child is a child symbol of #parent.
+/// Each level spans all of its occurrences and is selected at the first one
+#[allow(deprecated)]
+fn construct_tag_tree(parent: Option<&str>, tags: &[MDTag]) -> Vec<DocumentSymbol> {
+    tags.iter()
+        .flat_map(|tag| {
+            let rest = match parent {
+                Some(parent) => tag.tag_ref.strip_prefix(parent)?.strip_prefix('/')?,
+                None => tag.tag_ref.as_str(),
+            };
+            let segment = rest.split('/').next()?;
+
+            Some(match parent {
+                Some(parent) => format!("{}/{}", parent, segment),
+                None => segment.to_string(),
+            })
+        })
+        .unique()
+        .flat_map(|level| {
+            let occurrences = tags
+                .iter()
+                .filter(|tag| {
+                    tag.tag_ref == level
+                        || tag
+                            .tag_ref
+                            .strip_prefix(&level)
+                            .is_some_and(|rest| rest.starts_with('/'))
+                })
+                .map(|tag| *tag.range)
+                .collect_vec();
+
+            let first = occurrences
+                .iter()
+                .min_by_key(|range| (range.start.line, range.start.character))?;
+            let last = occurrences
+                .iter()
+                .max_by_key(|range| (range.end.line, range.end.character))?;
+
+            let children = construct_tag_tree(Some(&level), tags);
+
+            Some(DocumentSymbol {
+                name: match parent {
+                    Some(parent) => level[parent.len() + 1..].to_string(),
+                    None => format!("#{}", level),
+                },
+                kind: SymbolKind::CONSTANT,
+                deprecated: None,
+                tags: None,
+                range: Range {
+                    start: first.start,
+                    end: last.end,
+                },
+                detail: Some(format!("#{}", level)),
+                selection_range: *first,
+                children: (!children.is_empty()).then_some(children),
+            })
+        })
+        .collect()
 }
 
 #[derive(PartialEq, Debug)]
@@ -139,6 +212,7 @@ fn construct_tree(headings: &[MDHeading]) -> Option<Vec<Node>> {
     }
 }
 
+#[allow(deprecated)]
 fn map_to_lsp_tree(tree: Vec<Node>) -> Vec<DocumentSymbol> {
     tree.into_iter()
         .map(|node| DocumentSymbol {
@@ -156,9 +230,11 @@ fn map_to_lsp_tree(tree: Vec<Node>) -> Vec<DocumentSymbol> {
 
 #[cfg(test)]
 mod test {
+    use tower_lsp::lsp_types::{DocumentSymbol, Position, Range};
+
     use crate::{
         symbol,
-        vault::{HeadingLevel, MDHeading},
+        vault::{HeadingLevel, MDHeading, MDTag},
     };
 
     #[test]
@@ -327,4 +403,44 @@ mod test {
 
         assert_eq!(tree, Some(expected))
     }
+
+    #[test]
+    fn test_tag_tree() {
+        let tag = |tag_ref: &str, line| MDTag {
+            tag_ref: tag_ref.to_string(),
+            range: Range {
+                start: Position { line, character: 0 },
+                end: Position { line, character: 5 },
+            }
+            .into(),
+        };
+
+        let tags = vec![
+            tag("project/alpha", 1),
+            tag("area", 2),
+            tag("project/beta/x", 3),
+        ];
+
+        let tree = super::construct_tag_tree(None, &tags);
+
+        let names = |symbols: &[DocumentSymbol]| {
+            symbols
+                .iter()
+                .map(|symbol| symbol.name.clone())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(names(&tree), vec!["#project", "#area"]);
+        assert_eq!(tree[0].range.start.line, 1);
+        assert_eq!(tree[0].range.end.line, 3);
+
+        let project_children = tree[0].children.as_ref().unwrap();
+        assert_eq!(names(project_children), vec!["alpha", "beta"]);
+        assert_eq!(project_children[1].detail.as_deref(), Some("#project/beta"));
+        assert_eq!(
+            names(project_children[1].children.as_ref().unwrap()),
+            vec!["x"]
+        );
+        assert!(tree[1].children.is_none());
+    }
 }
@@ -0,0 +1,211 @@
+use std::path::Path;
+
+use chrono::NaiveDate;
+use itertools::Itertools;
+use pathdiff::diff_paths;
+use serde::{Deserialize, Serialize};
+use tower_lsp::lsp_types::ClientCapabilities;
+
+use crate::{
+    config::Settings,
+    vault::{MDTask, Vault},
+};
+
+/// Filters for the tasks in the vault; every filter that is set must match
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct TaskQuery {
+    /// The task has this tag or a tag nested under it
+    pub tag: Option<String>,
+    /// The file is in this folder, relative to the vault root
+    pub folder: Option<String>,
+    /// Due strictly before this date
+    pub due_before: Option<NaiveDate>,
+    /// Due strictly after this date
+    pub due_after: Option<NaiveDate>,
+    /// Include done, cancelled and deferred tasks
+    pub include_closed: bool,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskResult {
+    /// relative to the vault root
+    pub path: String,
+    /// zero based, as in lsp positions
+    pub line: u32,
+    pub status: char,
+    pub text: String,
+    pub tags: Vec<String>,
+    pub due: Option<NaiveDate>,
+}
+
+impl TaskQuery {
+    pub fn matches(&self, root_dir: &Path, path: &Path, task: &MDTask) -> bool {
+        let status = self.include_closed || task.status.is_open();
+
+        let tag = match &self.tag {
+            Some(query_tag) => {
+                let query_tag = query_tag.trim_start_matches('#');
+                task.tags.iter().any(|tag| {
+                    tag == query_tag
+                        || tag
+                            .strip_prefix(query_tag)
+                            .is_some_and(|rest| rest.starts_with('/'))
+                })
+            }
+            None => true,
+        };
+
+        let folder = match &self.folder {
+            Some(folder) => path
+                .strip_prefix(root_dir)
+                .is_ok_and(|relative| relative.starts_with(folder.trim_matches('/'))),
+            None => true,
+        };
+
+        let due_before = match self.due_before {
+            Some(before) => task.due.is_some_and(|due| due < before),
+            None => true,
+        };
+        let due_after = match self.due_after {
+            Some(after) => task.due.is_some_and(|due| due > after),
+            None => true,
+        };
+
+        status && tag && folder && due_before && due_after
+    }
+}
+
+/// Matching tasks ordered by due date, then by file and line; tasks without a due date are last
+pub fn query_tasks(vault: &Vault, query: &TaskQuery) -> Vec<TaskResult> {
+    vault
+        .md_files
+        .iter()
+        .flat_map(|(path, md_file)| md_file.tasks.iter().map(move |task| (path, task)))
+        .filter(|(path, task)| query.matches(vault.root_dir(), path, task))
+        .flat_map(|(path, task)| {
+            Some(TaskResult {
+                path: diff_paths(path, vault.root_dir())?.to_str()?.to_string(),
+                line: task.range.start.line,
+                status: task.status.to_char(),
+                text: task.text.clone(),
+                tags: task.tags.clone(),
+                due: task.due,
+            })
+        })
+        .sorted_by(|a, b| {
+            (a.due.is_none(), a.due, &a.path, a.line).cmp(&(
+                b.due.is_none(),
+                b.due,
+                &b.path,
+                b.line,
+            ))
+        })
+        .collect()
+}
+
+const CLI_USAGE: &str = "usage: markdown-oxide tasks [--tag TAG] [--folder FOLDER] [--due-before YYYY-MM-DD] [--due-after YYYY-MM-DD] [--all] [VAULT]";
+
+/// `markdown-oxide tasks ...`; prints the matching tasks as `path:line: - [ ] text`
+pub fn cli(args: &[String]) -> Result<(), String> {
+    let mut query = TaskQuery::default();
+    let mut root_dir = None;
+
+    let parse_date = |value: Option<&String>| {
+        value
+            .and_then(|value| NaiveDate::parse_from_str(value, "%Y-%m-%d").ok())
+            .ok_or_else(|| CLI_USAGE.to_string())
+    };
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--tag" => query.tag = Some(args.next().ok_or(CLI_USAGE)?.clone()),
+            "--folder" => query.folder = Some(args.next().ok_or(CLI_USAGE)?.clone()),
+            "--due-before" => query.due_before = Some(parse_date(args.next())?),
+            "--due-after" => query.due_after = Some(parse_date(args.next())?),
+            "--all" => query.include_closed = true,
+            "--help" | "-h" => return Err(CLI_USAGE.to_string()),
+            dir if root_dir.is_none() && !dir.starts_with('-') => root_dir = Some(dir.into()),
+            _ => return Err(CLI_USAGE.to_string()),
+        }
+    }
+
+    let root_dir = match root_dir {
+        Some(dir) => dir,
+        None => std::env::current_dir().map_err(|e| e.to_string())?,
+    };
+
+    let settings =
+        Settings::new(&root_dir, &ClientCapabilities::default()).map_err(|e| e.to_string())?;
+    let vault = Vault::construct_vault(&settings, &root_dir).map_err(|e| e.to_string())?;
+
+    for task in query_tasks(&vault, &query) {
+        println!(
+            "{}:{}: - [{}] {}",
+            task.path,
+            task.line + 1,
+            task.status,
+            task.text
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use chrono::NaiveDate;
+    use itertools::Itertools;
+
+    use crate::vault::MDTask;
+
+    use super::TaskQuery;
+
+    #[test]
+    fn query_filters() {
+        let text = "- [ ] one #project/alpha 📅 2026-10-20\n- [x] two #project\n- [ ] three #other";
+        let tasks = MDTask::new(text).collect_vec();
+        let root = Path::new("/vault");
+        let path = Path::new("/vault/work/note.md");
+
+        let matching = |query: &TaskQuery| {
+            tasks
+                .iter()
+                .filter(|task| query.matches(root, path, task))
+                .map(|task| task.text.split(' ').next().unwrap_or_default())
+                .collect_vec()
+        };
+
+        assert_eq!(matching(&TaskQuery::default()), vec!["one", "three"]);
+
+        assert_eq!(
+            matching(&TaskQuery {
+                tag: Some("#project".into()),
+                include_closed: true,
+                ..Default::default()
+            }),
+            vec!["one", "two"]
+        );
+
+        assert_eq!(
+            matching(&TaskQuery {
+                due_before: NaiveDate::from_ymd_opt(2026, 10, 21),
+                folder: Some("work".into()),
+                ..Default::default()
+            }),
+            vec!["one"]
+        );
+
+        assert_eq!(
+            matching(&TaskQuery {
+                folder: Some("personal".into()),
+                ..Default::default()
+            }),
+            Vec::<&str>::new()
+        );
+    }
+}
@@ -0,0 +1,185 @@
+use std::{
+    collections::HashMap,
+    fmt::Write,
+    path::{Path, PathBuf},
+};
+
+use chrono::{Local, NaiveDateTime, TimeZone};
+use itertools::Itertools;
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+use walkdir::WalkDir;
+
+use crate::config::{convert_momentjs_to_chrono_format, Settings};
+
+/// Read a template by its path relative to the vault root; the `.md` extension is optional, as in Obsidian
+pub fn read_template(root_dir: &Path, template: &str) -> Option<String> {
+    if template.trim().is_empty() {
+        return None;
+    }
+
+    let path = root_dir.join(template);
+    let path = match path.extension() {
+        Some(ext) if ext == "md" => path,
+        _ => root_dir.join(format!("{}.md", template)),
+    };
+
+    std::fs::read_to_string(path).ok()
+}
+
+/// Expand the Obsidian template variables `{{title}}`, `{{date}}`, `{{time}}`, `{{date:FORMAT}}` and `{{time:FORMAT}}`,
+/// where FORMAT is a moment.js format, and any custom `{{variable}}` that is given; other variables, and dates in a format
+/// that can not be written, are left as they are
+pub fn expand_template(
+    template: &str,
+    title: &str,
+    datetime: NaiveDateTime,
+    variables: &HashMap<String, String>,
+) -> String {
+    static VARIABLE_RE: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"\{\{\s*(?<name>[\w\-]+)\s*(:(?<format>[^}]*))?\}\}").unwrap());
+
+    VARIABLE_RE
+        .replace_all(template, |captures: &Captures| {
+            let name = captures.name("name").map(|name| name.as_str());
+            let format = captures
+                .name("format")
+                .map(|format| convert_momentjs_to_chrono_format(format.as_str().trim()));
+
+            match (name.map(|name| name.to_lowercase()).as_deref(), format) {
+                (Some("title"), _) => title.to_string(),
+                (Some("date") | Some("time"), Some(format)) => {
+                    format_datetime(datetime, &format).unwrap_or_else(|| captures[0].to_string())
+                }
+                (Some("date"), None) => datetime.format("%Y-%m-%d").to_string(),
+                (Some("time"), None) => datetime.format("%H:%M").to_string(),
+                _ => name
+                    .and_then(|name| variables.get(name))
+                    .cloned()
+                    .unwrap_or_else(|| captures[0].to_string()),
+            }
+        })
+        .to_string()
+}
+
+/// The local datetime in the chrono format, which may include the offset; None if the format is invalid
+fn format_datetime(datetime: NaiveDateTime, format: &str) -> Option<String> {
+    let mut formatted = String::new();
+
+    match Local.from_local_datetime(&datetime).earliest() {
+        Some(local) => write!(formatted, "{}", local.format(format)),
+        None => write!(formatted, "{}", datetime.format(format)),
+    }
+    .ok()?;
+
+    Some(formatted)
+}
+
+/// A note template in the templates folder
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    /// The path relative to the templates folder, without the extension; `meeting` or `work/project`
+    pub name: String,
+    pub path: PathBuf,
+}
+
+impl Template {
+    pub fn content(
+        &self,
+        title: &str,
+        datetime: NaiveDateTime,
+        variables: &HashMap<String, String>,
+    ) -> Option<String> {
+        let template = std::fs::read_to_string(&self.path).ok()?;
+
+        Some(expand_template(&template, title, datetime, variables))
+    }
+}
+
+/// The markdown files in the templates folder, ordered by name
+pub fn list_templates(root_dir: &Path, settings: &Settings) -> Vec<Template> {
+    let templates_folder = root_dir.join(&settings.templates_folder);
+
+    WalkDir::new(&templates_folder)
+        .into_iter()
+        .filter_entry(|e| {
+            !e.file_name()
+                .to_str()
+                .map(|s| s.starts_with('.'))
+                .unwrap_or(false)
+        })
+        .flatten()
+        .filter(|entry| {
+            entry.file_type().is_file() && entry.path().extension().is_some_and(|ext| ext == "md")
+        })
+        .flat_map(|entry| {
+            let name = entry
+                .path()
+                .strip_prefix(&templates_folder)
+                .ok()?
+                .with_extension("")
+                .to_str()?
+                .to_string();
+
+            Some(Template {
+                name,
+                path: entry.into_path(),
+            })
+        })
+        .sorted_by(|a, b| a.name.cmp(&b.name))
+        .collect()
+}
+
+/// Arguments of the `newFromTemplate` command
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct NewFromTemplate {
+    /// The template name; the client is asked to choose one if this is not set
+    pub template: Option<String>,
+    /// The title, which is also the file name; `Untitled` if this is not set
+    pub title: Option<String>,
+    /// The folder for the new note, relative to the vault root; the new file folder if this is not set
+    pub folder: Option<String>,
+    /// Values for custom `{{variable}}`s in the template
+    pub variables: HashMap<String, String>,
+}
+
+/// `Untitled`, or `Untitled 1`, `Untitled 2`, ... if it already exists, as in Obsidian
+pub fn untitled_note_path(folder: &Path) -> PathBuf {
+    (0..)
+        .map(|n| match n {
+            0 => folder.join("Untitled.md"),
+            n => folder.join(format!("Untitled {}.md", n)),
+        })
+        .find(|path| !path.exists())
+        .unwrap_or_else(|| folder.join("Untitled.md"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use chrono::NaiveDate;
+
+    use super::expand_template;
+
+    #[test]
+    fn expands_variables() {
+        let datetime = NaiveDate::from_ymd_opt(2026, 10, 18)
+            .unwrap()
+            .and_hms_opt(9, 5, 0)
+            .unwrap();
+
+        let template = "# {{title}}\n\nCreated {{date}} {{time}}\n{{date:dddd, MMMM D}} week {{ date:GGGG-[W]WW }}\n{{unknown}}\nProject: {{project}}";
+        let variables = HashMap::from([("project".to_string(), "Oxide".to_string())]);
+
+        assert_eq!(
+            expand_template(template, "2026-10-18", datetime, &variables),
+            "# 2026-10-18\n\nCreated 2026-10-18 09:05\nSunday, October 18 week 2026-W42\n{{unknown}}\nProject: Oxide"
+        );
+
+        let offset = expand_template("{{date:ZZ}}", "", datetime, &variables);
+        assert!(offset.starts_with(['+', '-']) && offset.len() == 5);
+    }
+}
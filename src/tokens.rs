@@ -1,84 +1,474 @@
-use std::{collections::HashSet, iter, path::Path};
+use std::{collections::HashSet, path::Path};
 
 use itertools::Itertools;
+use once_cell::sync::Lazy;
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
-use tower_lsp::lsp_types::{SemanticToken, SemanticTokensParams, SemanticTokensResult};
+use regex::Regex;
+use ropey::Rope;
+use tower_lsp::lsp_types::{
+    Range, SemanticToken, SemanticTokenModifier, SemanticTokenType, SemanticTokensEdit,
+    SemanticTokensLegend,
+};
 
-use crate::{config::Settings, diagnostics::path_unresolved_references, vault::Vault};
+use crate::{
+    config::Settings,
+    diagnostics::path_unresolved_references,
+    vault::{MyRange, Rangeable, Reference, Vault},
+};
 
+/// The standard types that editors and themes already style; the kinds of tokens are told apart by their modifiers.
+/// The order of the types is the index sent to the client; see `TokenType::standard_type`
+const STANDARD_TOKEN_TYPES: [SemanticTokenType; 6] = [
+    SemanticTokenType::DECORATOR,
+    SemanticTokenType::COMMENT,
+    SemanticTokenType::MACRO,
+    SemanticTokenType::KEYWORD,
+    SemanticTokenType::PROPERTY,
+    SemanticTokenType::ENUM_MEMBER,
+];
+
+/// A type for each kind of token, for clients that style them; see `custom_semantic_token_types`.
+/// The order of the types is the index sent to the client; see `TokenType`
+const CUSTOM_TOKEN_TYPES: [SemanticTokenType; 9] = [
+    SemanticTokenType::new("wikiLink"),
+    SemanticTokenType::new("markdownLink"),
+    SemanticTokenType::new("headingLink"),
+    SemanticTokenType::new("blockLink"),
+    SemanticTokenType::new("tag"),
+    SemanticTokenType::new("footnote"),
+    SemanticTokenType::new("callout"),
+    SemanticTokenType::new("frontmatterKey"),
+    SemanticTokenType::new("blockId"),
+];
+
+/// The order of the modifiers is the bit sent to the client; see `UNRESOLVED`, `EMBED` and `EXTERNAL`. They are followed
+/// by a modifier for each kind of token, in the order of `TokenType`
+const TOKEN_MODIFIERS: [SemanticTokenModifier; 12] = [
+    SemanticTokenModifier::new("unresolved"),
+    SemanticTokenModifier::new("embed"),
+    SemanticTokenModifier::new("external"),
+    SemanticTokenModifier::new("wikiLink"),
+    SemanticTokenModifier::new("markdownLink"),
+    SemanticTokenModifier::new("headingLink"),
+    SemanticTokenModifier::new("blockLink"),
+    SemanticTokenModifier::new("tag"),
+    SemanticTokenModifier::new("footnote"),
+    SemanticTokenModifier::new("callout"),
+    SemanticTokenModifier::new("frontmatterKey"),
+    SemanticTokenModifier::new("blockId"),
+];
+
+const UNRESOLVED: u32 = 1 << 0;
+const EMBED: u32 = 1 << 1;
+const EXTERNAL: u32 = 1 << 2;
+/// The bit of the modifier of the first kind of token
+const KIND_MODIFIERS_START: u32 = 3;
+
+pub fn legend(settings: &Settings) -> SemanticTokensLegend {
+    SemanticTokensLegend {
+        token_types: match settings.custom_semantic_token_types {
+            true => CUSTOM_TOKEN_TYPES.to_vec(),
+            false => STANDARD_TOKEN_TYPES.to_vec(),
+        },
+        token_modifiers: TOKEN_MODIFIERS.to_vec(),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TokenType {
+    WikiLink,
+    MarkdownLink,
+    HeadingLink,
+    BlockLink,
+    Tag,
+    Footnote,
+    Callout,
+    FrontmatterKey,
+    BlockId,
+}
+
+impl TokenType {
+    /// The index of the type in `STANDARD_TOKEN_TYPES`; unresolved links are comments, as they have always been
+    fn standard_type(self, modifiers: u32) -> u32 {
+        match self {
+            TokenType::WikiLink
+            | TokenType::MarkdownLink
+            | TokenType::HeadingLink
+            | TokenType::BlockLink
+            | TokenType::Footnote
+                if modifiers & UNRESOLVED != 0 =>
+            {
+                1
+            }
+            TokenType::WikiLink
+            | TokenType::MarkdownLink
+            | TokenType::HeadingLink
+            | TokenType::BlockLink
+            | TokenType::Footnote => 0,
+            TokenType::Tag => 2,
+            TokenType::Callout => 3,
+            TokenType::FrontmatterKey => 4,
+            TokenType::BlockId => 5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Token {
+    range: MyRange,
+    token_type: TokenType,
+    modifiers: u32,
+}
+
+impl Rangeable for Token {
+    fn range(&self) -> &MyRange {
+        &self.range
+    }
+}
+
+/// All of the tokens in the file, encoded relative to each other as the spec requires
 pub fn semantic_tokens_full(
     vault: &Vault,
     path: &Path,
-    _params: SemanticTokensParams,
     settings: &Settings,
-) -> Option<SemanticTokensResult> {
+) -> Option<Vec<SemanticToken>> {
     if !settings.semantic_tokens {
         return None;
     }
 
-    let references_in_file = vault.select_references(Some(path))?;
+    Some(encode(&file_tokens(vault, path)?, settings))
+}
 
-    let path_unresolved: Option<HashSet<_>> =
-        path_unresolved_references(vault, path).map(|thing| {
-            thing
+/// Tokens that are at least partially in the range; the positions are still relative to the start of the file
+pub fn semantic_tokens_range(
+    vault: &Vault,
+    path: &Path,
+    range: Range,
+    settings: &Settings,
+) -> Option<Vec<SemanticToken>> {
+    if !settings.semantic_tokens {
+        return None;
+    }
+
+    let tokens = file_tokens(vault, path)?
+        .into_iter()
+        .filter(|token| {
+            (token.range.end.line, token.range.end.character)
+                >= (range.start.line, range.start.character)
+                && (token.range.start.line, token.range.start.character)
+                    <= (range.end.line, range.end.character)
+        })
+        .collect_vec();
+
+    Some(encode(&tokens, settings))
+}
+
+/// The edit turning the previous tokens into the current ones: everything between the common prefix and suffix is replaced.
+/// Indices are in the flattened integer array, where every token is five integers.
+pub fn semantic_tokens_delta(
+    previous: &[SemanticToken],
+    current: &[SemanticToken],
+) -> Vec<SemanticTokensEdit> {
+    let prefix = previous
+        .iter()
+        .zip(current)
+        .take_while(|(previous, current)| previous == current)
+        .count();
+
+    let suffix = previous[prefix..]
+        .iter()
+        .rev()
+        .zip(current[prefix..].iter().rev())
+        .take_while(|(previous, current)| previous == current)
+        .count();
+
+    let deleted = previous.len() - prefix - suffix;
+    let inserted = &current[prefix..current.len() - suffix];
+
+    if deleted == 0 && inserted.is_empty() {
+        return vec![];
+    }
+
+    vec![SemanticTokensEdit {
+        start: (prefix * 5) as u32,
+        delete_count: (deleted * 5) as u32,
+        data: (!inserted.is_empty()).then(|| inserted.to_vec()),
+    }]
+}
+
+fn file_tokens(vault: &Vault, path: &Path) -> Option<Vec<Token>> {
+    let md_file = vault.md_files.get(path)?;
+    let rope = vault.ropes.get(path)?;
+    let text = rope.to_string();
+
+    let path_unresolved: HashSet<_> = path_unresolved_references(vault, path)
+        .map(|unresolved| {
+            unresolved
                 .into_par_iter()
                 .map(|(_, reference)| reference)
                 .collect()
+        })
+        .unwrap_or_default();
+
+    let in_codeblock = |token: &Token| {
+        md_file
+            .codeblocks
+            .iter()
+            .any(|codeblock| codeblock.includes(token))
+    };
+
+    // links to urls and attachments, and tokens found in the text rather than in the parsed vault; code blocks have to be
+    // excluded from the text tokens here
+    let text_tokens = md_file
+        .external_links
+        .iter()
+        .map(|link| Token {
+            range: link.range,
+            token_type: if is_wiki_link(rope, &link.range) {
+                TokenType::WikiLink
+            } else {
+                TokenType::MarkdownLink
+            },
+            modifiers: if link.is_url { EXTERNAL } else { 0 }
+                | if is_embed(rope, &link.range) {
+                    EMBED
+                } else {
+                    0
+                },
+        })
+        .chain(callouts(&text, rope))
+        .chain(frontmatter_keys(&text, rope))
+        .filter(|token| !in_codeblock(token));
+
+    let references = md_file.references.iter().map(|reference| Token {
+        range: *reference.range(),
+        token_type: match reference {
+            Reference::WikiFileLink(..) => TokenType::WikiLink,
+            Reference::MDFileLink(..) | Reference::LinkRef(..) => TokenType::MarkdownLink,
+            Reference::WikiHeadingLink(..) | Reference::MDHeadingLink(..) => TokenType::HeadingLink,
+            Reference::WikiIndexedBlockLink(..) | Reference::MDIndexedBlockLink(..) => {
+                TokenType::BlockLink
+            }
+            Reference::Tag(..) => TokenType::Tag,
+            Reference::Footnote(..) => TokenType::Footnote,
+        },
+        modifiers: if path_unresolved.contains(reference) {
+            UNRESOLVED
+        } else {
+            0
+        } | if is_embed(rope, reference.range()) {
+            EMBED
+        } else {
+            0
+        },
+    });
+
+    let block_ids = md_file.indexed_blocks.iter().map(|block| Token {
+        range: block.range,
+        token_type: TokenType::BlockId,
+        modifiers: 0,
+    });
+
+    let tokens = text_tokens
+        .chain(references)
+        .chain(block_ids)
+        // clients are not required to support multiline tokens
+        .filter(|token| {
+            token.range.start.line == token.range.end.line
+                && token.range.start.character < token.range.end.character
+        })
+        .sorted_by_key(|token| (token.range.start.line, token.range.start.character))
+        .fold(vec![], |mut acc: Vec<Token>, token| {
+            match acc.last() {
+                Some(last)
+                    if last.range.end.line == token.range.start.line
+                        && last.range.end.character > token.range.start.character => {}
+                _ => acc.push(token),
+            }
+            acc
         });
 
-    let tokens = references_in_file
+    Some(tokens)
+}
+
+fn is_embed(rope: &Rope, range: &MyRange) -> bool {
+    range.start.character.checked_sub(1).and_then(|before| {
+        rope.get_line(range.start.line as usize)?
+            .get_char(before as usize)
+    }) == Some('!')
+}
+
+fn is_wiki_link(rope: &Rope, range: &MyRange) -> bool {
+    rope.get_line(range.start.line as usize)
+        .is_some_and(|line| {
+            let start = range.start.character as usize;
+            line.get_char(start) == Some('[') && line.get_char(start + 1) == Some('[')
+        })
+}
+
+fn callouts<'a>(text: &'a str, rope: &'a Rope) -> impl Iterator<Item = Token> + 'a {
+    static CALLOUT_RE: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"(?m)^(\s*>\s*)+(?<callout>\[![^\]\s]+\][+-]?)").unwrap());
+
+    CALLOUT_RE
+        .captures_iter(text)
+        .flat_map(|captures| captures.name("callout"))
+        .map(|callout| Token {
+            range: MyRange::from_range(rope, callout.range()),
+            token_type: TokenType::Callout,
+            modifiers: 0,
+        })
+}
+
+fn frontmatter_keys<'a>(text: &'a str, rope: &'a Rope) -> impl Iterator<Item = Token> + 'a {
+    static FRONTMATTER_RE: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"^---\r?\n(?<metadata>(\n|.)*?)\r?\n---").unwrap());
+    static KEY_RE: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"(?m)^\s*(?<key>[A-Za-z_][\w\- ]*):(\s|$)").unwrap());
+
+    FRONTMATTER_RE
+        .captures(text)
+        .and_then(|captures| captures.name("metadata"))
         .into_iter()
-        .sorted_by_key(|(_, reference)| {
-            (
-                reference.data().range.start.line,
-                reference.data().range.start.character,
-            )
+        .flat_map(move |metadata| {
+            KEY_RE
+                .captures_iter(metadata.as_str())
+                .flat_map(|captures| captures.name("key"))
+                .map(move |key| {
+                    let start = metadata.start() + key.start();
+                    Token {
+                        range: MyRange::from_range(rope, start..start + key.len()),
+                        token_type: TokenType::FrontmatterKey,
+                        modifiers: 0,
+                    }
+                })
         })
-        .fold(vec![], |acc, (_path, reference)| {
-            let range = reference.data().range;
-
-            let is_unresolved = path_unresolved
-                .as_ref()
-                .is_some_and(|unresolved| unresolved.contains(reference));
-
-            match acc[..] {
-                [] => vec![(
-                    reference,
-                    SemanticToken {
-                        delta_line: range.start.line,
-                        delta_start: range.start.character,
-                        length: range.end.character - range.start.character,
-                        token_type: if is_unresolved { 1 } else { 0 },
-                        token_modifiers_bitset: 0,
-                    },
-                )],
-                [.., (prev_ref, _)] => acc
-                    .into_iter()
-                    .chain(iter::once((
-                        reference,
-                        SemanticToken {
-                            delta_line: range.start.line - prev_ref.data().range.start.line,
-                            delta_start: if range.start.line == prev_ref.data().range.start.line {
-                                range.start.character - prev_ref.data().range.start.character
-                            } else {
-                                range.start.character
-                            },
-                            length: range.end.character - range.start.character,
-                            token_type: if is_unresolved { 1 } else { 0 },
-                            token_modifiers_bitset: 0,
-                        },
-                    )))
-                    .collect_vec(),
-            }
+}
+
+/// Tokens must be sorted and single line
+fn encode(tokens: &[Token], settings: &Settings) -> Vec<SemanticToken> {
+    tokens
+        .iter()
+        .scan(None, |prev: &mut Option<Range>, token| {
+            let range = *token.range;
+
+            let (delta_line, delta_start) = match prev {
+                Some(prev) if prev.start.line == range.start.line => {
+                    (0, range.start.character - prev.start.character)
+                }
+                Some(prev) => (range.start.line - prev.start.line, range.start.character),
+                None => (range.start.line, range.start.character),
+            };
+
+            *prev = Some(range);
+
+            Some(SemanticToken {
+                delta_line,
+                delta_start,
+                length: range.end.character - range.start.character,
+                token_type: match settings.custom_semantic_token_types {
+                    true => token.token_type as u32,
+                    false => token.token_type.standard_type(token.modifiers),
+                },
+                token_modifiers_bitset: token.modifiers
+                    | 1 << (KIND_MODIFIERS_START + token.token_type as u32),
+            })
         })
-        .into_par_iter()
-        .map(|(_, token)| token)
-        .collect::<Vec<_>>(); // TODO: holy this is bad
-
-    Some(SemanticTokensResult::Tokens(
-        tower_lsp::lsp_types::SemanticTokens {
-            result_id: None,
-            data: tokens,
-        },
-    ))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use ropey::Rope;
+    use tower_lsp::lsp_types::{SemanticToken, SemanticTokensEdit};
+
+    use crate::{config::Settings, vault::Vault};
+
+    use super::{frontmatter_keys, semantic_tokens_delta, semantic_tokens_full};
+
+    fn token(delta_line: u32, delta_start: u32) -> SemanticToken {
+        SemanticToken {
+            delta_line,
+            delta_start,
+            length: 4,
+            token_type: 0,
+            token_modifiers_bitset: 0,
+        }
+    }
+
+    #[test]
+    fn delta_replaces_changed_tokens() {
+        let previous = vec![token(0, 0), token(1, 2), token(1, 0)];
+        let current = vec![token(0, 0), token(2, 3), token(0, 8), token(1, 0)];
+
+        assert_eq!(
+            semantic_tokens_delta(&previous, &current),
+            vec![SemanticTokensEdit {
+                start: 5,
+                delete_count: 5,
+                data: Some(vec![token(2, 3), token(0, 8)]),
+            }]
+        );
+
+        assert_eq!(semantic_tokens_delta(&current, &current), vec![]);
+
+        assert_eq!(
+            semantic_tokens_delta(&current, &previous[..2]),
+            vec![SemanticTokensEdit {
+                start: 5,
+                delete_count: 15,
+                data: Some(vec![token(1, 2)]),
+            }]
+        );
+    }
+
+    #[test]
+    fn standard_types_with_kind_modifiers() {
+        let root_dir = Path::new("/vault");
+        let mut settings = Settings::for_vault(root_dir);
+        let vault = Vault::from_texts(
+            &settings,
+            root_dir,
+            &[("note.md", "[[note]] [[missing]] #tag")],
+        );
+        let path = root_dir.join("note.md");
+        let types = |settings: &Settings| {
+            semantic_tokens_full(&vault, &path, settings)
+                .unwrap()
+                .into_iter()
+                .map(|token| (token.token_type, token.token_modifiers_bitset))
+                .collect::<Vec<_>>()
+        };
+
+        // decorator, comment for the unresolved link, and macro, with the wikiLink and tag modifiers
+        assert_eq!(
+            types(&settings),
+            vec![(0, 1 << 3), (1, 1 | 1 << 3), (2, 1 << 7)]
+        );
+
+        settings.custom_semantic_token_types = true;
+        assert_eq!(
+            types(&settings),
+            vec![(0, 1 << 3), (0, 1 | 1 << 3), (4, 1 << 7)]
+        );
+    }
+
+    #[test]
+    fn frontmatter_keys_with_crlf() {
+        let text = "---\r\ntitle: Note\r\ntags:\r\n  - a\r\n---\r\nbody: not a key";
+        let rope = Rope::from_str(text);
+        let keys = frontmatter_keys(text, &rope)
+            .map(|token| {
+                (
+                    token.range.start.line,
+                    token.range.start.character,
+                    token.range.end.character,
+                )
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(keys, vec![(1, 0, 5), (2, 0, 4)]);
+    }
 }
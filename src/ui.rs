@@ -3,7 +3,7 @@ use std::path::Path;
 use itertools::Itertools;
 use tower_lsp::lsp_types::{MarkupContent, MarkupKind};
 
-use crate::vault::{get_obsidian_ref_path, Preview, Reference, Referenceable, Vault};
+use crate::vault::{get_obsidian_ref_path, MDTag, Preview, Reference, Referenceable, Vault};
 
 fn referenceable_string(vault: &Vault, referenceables: &[Referenceable]) -> Option<String> {
     let referenceable = referenceables.first()?;
@@ -22,12 +22,23 @@ fn referenceable_string(vault: &Vault, referenceables: &[Referenceable]) -> Opti
         None => "No Preview".into(),
     };
 
-    let backlinks_preview = match referenceables
-        .iter()
-        .flat_map(|i| vault.select_references_for_referenceable(i))
-        .flatten()
-        .collect_vec()
-    {
+    let backlinks_preview = backlinks_string(
+        vault,
+        referenceables
+            .iter()
+            .flat_map(|i| vault.select_references_for_referenceable(i))
+            .flatten()
+            .collect_vec(),
+    );
+
+    Some(format!(
+        "{}\n\n`...`\n\n---\n\n# Backlinks\n\n{}",
+        written_text_preview, backlinks_preview
+    ))
+}
+
+fn backlinks_string(vault: &Vault, references: Vec<(&Path, &Reference)>) -> String {
+    match references {
         references if !references.is_empty() => references
             .into_iter()
             .take(20)
@@ -42,11 +53,43 @@ fn referenceable_string(vault: &Vault, referenceables: &[Referenceable]) -> Opti
             })
             .join("\n"),
         _ => "No Backlinks".to_string(),
+    }
+}
+
+/// A tag and all of its nested tags: #parent aggregates #parent/child
+fn tag_string(vault: &Vault, reference_path: &Path, tag: &str) -> Option<String> {
+    let md_tag = MDTag {
+        tag_ref: tag.trim_start_matches('#').to_string(),
+        range: Default::default(),
+    };
+    let path_buf = reference_path.to_path_buf();
+    let referenceable = Referenceable::Tag(&path_buf, &md_tag);
+
+    let references = vault.select_references_for_referenceable(&referenceable)?;
+
+    let nested_tags = references
+        .iter()
+        .map(|(_, reference)| reference.data().reference_text.as_str())
+        .counts()
+        .into_iter()
+        .sorted()
+        .map(|(tag, count)| match count {
+            1 => format!("- `{}`: 1 reference", tag),
+            n => format!("- `{}`: {} references", tag, n),
+        })
+        .join("\n");
+
+    let num_references = match references.len() {
+        1 => "1 reference".to_string(),
+        n => format!("{} references", n),
     };
 
     Some(format!(
-        "{}\n\n`...`\n\n---\n\n# Backlinks\n\n{}",
-        written_text_preview, backlinks_preview
+        "`Tag:` `#{}`: {}\n\n# Nested Tags\n\n{}\n\n---\n\n# Backlinks\n\n{}",
+        md_tag.tag_ref,
+        num_references,
+        nested_tags,
+        backlinks_string(vault, references)
     ))
 }
 
@@ -54,7 +97,10 @@ pub fn preview_referenceable(
     vault: &Vault,
     referenceable: &Referenceable,
 ) -> Option<MarkupContent> {
-    let display = referenceable_string(vault, &[referenceable.clone()])?;
+    let display = match referenceable {
+        Referenceable::Tag(path, tag) => tag_string(vault, path, &tag.tag_ref)?,
+        _ => referenceable_string(vault, std::slice::from_ref(referenceable))?,
+    };
 
     Some(MarkupContent {
         kind: MarkupKind::Markdown,
@@ -88,6 +134,13 @@ pub fn preview_reference(
                 value: display,
             })
         }
-        Tag(_) => None,
+        Tag(data) => {
+            let display = tag_string(vault, reference_path, &data.reference_text)?;
+
+            Some(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: display,
+            })
+        }
     }
 }
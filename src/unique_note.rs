@@ -0,0 +1,143 @@
+use std::{
+    collections::HashMap,
+    fmt::Write as _,
+    fs::File,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use chrono::{
+    format::{parse, Parsed, StrftimeItems},
+    Duration, NaiveDateTime,
+};
+use serde::Deserialize;
+
+use crate::{
+    config::Settings,
+    templates::{expand_template, read_template},
+};
+
+/// Arguments of the `newUniqueNote` command
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct NewUniqueNote {
+    /// Appended to the prefix, as in `202610171230 Title`
+    pub title: Option<String>,
+    /// The prefix to use instead of formatting the current time; link completions pass the prefix that they inserted
+    pub prefix: Option<String>,
+    /// Open the note after creating it; true if this is not set
+    pub open: Option<bool>,
+}
+
+/// `202610171230 Title`, or only the prefix without a title
+pub fn unique_note_name(prefix: &str, title: Option<&str>) -> String {
+    match title.map(str::trim) {
+        Some(title) if !title.is_empty() => format!("{} {}", prefix, title),
+        _ => prefix.to_string(),
+    }
+}
+
+/// The file names in the unique notes folder
+pub fn unique_note_file_names(root_dir: &Path, settings: &Settings) -> Vec<String> {
+    std::fs::read_dir(root_dir.join(&settings.unique_notes_folder))
+        .map(|entries| {
+            entries
+                .flatten()
+                .flat_map(|entry| entry.file_name().to_str().map(String::from))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// The prefix for a new unique note. If one of the existing file names already has the prefix,
+/// the time is moved forward a minute at a time until the prefix is unused; None if the prefix format is invalid
+pub fn unique_note_prefix(
+    settings: &Settings,
+    now: NaiveDateTime,
+    existing: &[String],
+) -> Option<String> {
+    let format_prefix = |datetime: NaiveDateTime| {
+        let mut prefix = String::new();
+        write!(prefix, "{}", datetime.format(&settings.unique_note_prefix)).ok()?;
+        Some(prefix)
+    };
+
+    let now_prefix = format_prefix(now)?;
+
+    Some(
+        (0..60)
+            .flat_map(|minutes| format_prefix(now + Duration::try_minutes(minutes)?))
+            .find(|prefix| {
+                !existing.iter().any(|name| {
+                    *name == format!("{}.md", prefix) || name.starts_with(&format!("{} ", prefix))
+                })
+            })
+            .unwrap_or(now_prefix),
+    )
+}
+
+/// Whether a note name starts with a unique note prefix, as in `202610171230 Title`
+pub fn is_unique_note_name(settings: &Settings, name: &str) -> bool {
+    starts_with_prefix(&settings.unique_note_prefix, name)
+}
+
+fn starts_with_prefix(prefix_format: &str, name: &str) -> bool {
+    if prefix_format.is_empty() {
+        return false;
+    }
+
+    // the prefix is the words of the name up to the number of words in the format
+    let prefix_words = prefix_format.split(' ').count();
+    let prefix = name
+        .splitn(prefix_words + 1, ' ')
+        .take(prefix_words)
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let mut parsed = Parsed::new();
+    parse(&mut parsed, &prefix, StrftimeItems::new(prefix_format)).is_ok()
+}
+
+/// Create a unique note in the unique notes folder, filled from the unique note template, and return its path
+pub fn create_unique_note(
+    root_dir: &Path,
+    settings: &Settings,
+    prefix: &str,
+    title: Option<&str>,
+    now: NaiveDateTime,
+) -> std::io::Result<PathBuf> {
+    let name = unique_note_name(prefix, title);
+    let folder = root_dir.join(&settings.unique_notes_folder);
+    let path = folder.join(format!("{}.md", name));
+
+    let content = read_template(root_dir, &settings.unique_note_template)
+        .map(|template| expand_template(&template, &name, now, &HashMap::new()))
+        .unwrap_or_default();
+
+    std::fs::create_dir_all(&folder)?;
+    let mut file = File::create_new(&path)?;
+    file.write_all(content.as_bytes())?;
+
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{starts_with_prefix, unique_note_name};
+
+    #[test]
+    fn unique_note_prefixes() {
+        assert_eq!(
+            unique_note_name("202610171230", Some("Title")),
+            "202610171230 Title"
+        );
+        assert_eq!(unique_note_name("202610171230", Some(" ")), "202610171230");
+
+        assert!(starts_with_prefix("%Y%m%d%H%M", "202610171230 Title"));
+        assert!(starts_with_prefix("%Y%m%d%H%M", "202610171230"));
+        assert!(starts_with_prefix("%Y-%m-%d %H%M", "2026-10-17 1230 Title"));
+        assert!(!starts_with_prefix("%Y%m%d%H%M", "Title 202610171230"));
+        assert!(!starts_with_prefix("%Y%m%d%H%M", "2026"));
+        assert!(!starts_with_prefix("", "202610171230 Title"));
+    }
+}
@@ -0,0 +1,345 @@
+use std::{
+    cmp::Reverse,
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use super::{blocks::MDBlock, Block};
+use itertools::Itertools;
+use nucleo_matcher::{
+    pattern::{CaseMatching, Normalization, Pattern},
+    Matcher, Utf32Str,
+};
+use rayon::prelude::*;
+use ropey::Rope;
+
+/// Three lowercase characters of the text of a block
+type Trigram = [char; 3];
+
+/// An inverted index of the blocks of the vault by trigram, so that block completion only has to fuzzy match
+/// the blocks that contain the words of the query instead of every line of the vault. It is updated for each changed file
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct BlockIndex {
+    files: HashMap<PathBuf, FileBlocks>,
+    /// The files that have a block with the trigram
+    files_by_trigram: HashMap<Trigram, HashSet<PathBuf>>,
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+struct FileBlocks {
+    blocks: Vec<MDBlock>,
+    /// The indexes of the blocks with the trigram, in ascending order
+    by_trigram: HashMap<Trigram, Vec<usize>>,
+}
+
+impl BlockIndex {
+    pub fn new<'a>(ropes: impl IntoParallelIterator<Item = (&'a PathBuf, &'a Rope)>) -> BlockIndex {
+        let files = ropes
+            .into_par_iter()
+            .map(|(path, rope)| (path.clone(), FileBlocks::new(rope)))
+            .collect::<Vec<_>>();
+
+        files
+            .into_iter()
+            .fold(BlockIndex::default(), |mut index, (path, file_blocks)| {
+                index.insert(path, file_blocks);
+                index
+            })
+    }
+
+    /// Reindex the blocks of a changed file
+    pub fn update(&mut self, path: &Path, rope: &Rope) {
+        self.remove(path);
+        self.insert(path.to_path_buf(), FileBlocks::new(rope));
+    }
+
+    fn insert(&mut self, path: PathBuf, file_blocks: FileBlocks) {
+        for trigram in file_blocks.by_trigram.keys() {
+            self.files_by_trigram
+                .entry(*trigram)
+                .or_default()
+                .insert(path.clone());
+        }
+
+        self.files.insert(path, file_blocks);
+    }
+
+    fn remove(&mut self, path: &Path) {
+        let Some(file_blocks) = self.files.remove(path) else {
+            return;
+        };
+
+        for trigram in file_blocks.by_trigram.keys() {
+            if let Some(files) = self.files_by_trigram.get_mut(trigram) {
+                files.remove(path);
+                if files.is_empty() {
+                    self.files_by_trigram.remove(trigram);
+                }
+            }
+        }
+    }
+
+    /// The block of the file with the id
+    pub fn block(&self, path: &Path, index: &str) -> Option<Block<'_>> {
+        let (path, file_blocks) = self.files.get_key_value(path)?;
+
+        file_blocks
+            .blocks
+            .iter()
+            .find(|block| block.id.as_ref().is_some_and(|(id, _)| id == index))
+            .map(|block| block_of(path, block))
+    }
+
+    /// At most `limit` blocks that fuzzy match the query, best first. Only the blocks with every trigram of the query
+    /// are scored; if no block has all of them, like for a misspelled word, the blocks with at least half of them are
+    /// scored instead. A query without trigrams scores every block
+    pub fn select(&self, query: &str, limit: usize) -> Vec<Block<'_>> {
+        let query = query.to_lowercase();
+        let trigrams = trigrams(&query).collect::<HashSet<_>>();
+        let pattern = Pattern::parse(&query, CaseMatching::Ignore, Normalization::Smart);
+
+        let matches = match trigrams.is_empty() {
+            true => fuzzy_match(
+                &pattern,
+                self.files
+                    .iter()
+                    .flat_map(|(path, file_blocks)| {
+                        file_blocks
+                            .blocks
+                            .iter()
+                            .map(move |block| block_of(path, block))
+                    })
+                    .collect(),
+            ),
+            false => match fuzzy_match(&pattern, self.candidates(&trigrams)) {
+                matches if matches.is_empty() => {
+                    fuzzy_match(&pattern, self.partial_candidates(&trigrams))
+                }
+                matches => matches,
+            },
+        };
+
+        matches
+            .into_iter()
+            .sorted_by_key(|(_, score)| Reverse(*score))
+            .take(limit)
+            .map(|(block, _)| block)
+            .collect()
+    }
+
+    /// The blocks that have all of the trigrams
+    fn candidates(&self, trigrams: &HashSet<Trigram>) -> Vec<Block<'_>> {
+        let Some(postings) = trigrams
+            .iter()
+            .map(|trigram| self.files_by_trigram.get(trigram))
+            .collect::<Option<Vec<_>>>()
+        else {
+            return vec![];
+        };
+        let Some(rarest) = postings.iter().min_by_key(|files| files.len()) else {
+            return vec![];
+        };
+
+        rarest
+            .iter()
+            .filter(|path| postings.iter().all(|files| files.contains(*path)))
+            .flat_map(|path| self.files.get_key_value(path))
+            .flat_map(|(path, file_blocks)| {
+                file_blocks
+                    .candidates(trigrams)
+                    .map(move |block| block_of(path, block))
+            })
+            .collect()
+    }
+
+    /// The blocks that have at least half of the trigrams
+    fn partial_candidates(&self, trigrams: &HashSet<Trigram>) -> Vec<Block<'_>> {
+        let counts = trigrams
+            .iter()
+            .flat_map(|trigram| {
+                self.files_by_trigram
+                    .get(trigram)
+                    .map(|files| (trigram, files))
+            })
+            .flat_map(|(trigram, files)| {
+                files.iter().flat_map(move |path| {
+                    let (path, file_blocks) = self.files.get_key_value(path)?;
+                    Some(
+                        file_blocks
+                            .by_trigram
+                            .get(trigram)?
+                            .iter()
+                            .map(move |i| (path, *i)),
+                    )
+                })
+            })
+            .flatten()
+            .counts();
+
+        counts
+            .into_iter()
+            .filter(|(_, count)| count * 2 >= trigrams.len())
+            .flat_map(|((path, i), _)| {
+                let block = self.files.get(path)?.blocks.get(i)?;
+                Some(block_of(path, block))
+            })
+            .collect()
+    }
+}
+
+impl FileBlocks {
+    fn new(rope: &Rope) -> FileBlocks {
+        let blocks = MDBlock::new(&rope.to_string())
+            .filter(|block| !block.text.is_empty())
+            .collect::<Vec<_>>();
+
+        let by_trigram = blocks.iter().enumerate().fold(
+            HashMap::new(),
+            |mut by_trigram: HashMap<_, Vec<_>>, (i, block)| {
+                for trigram in trigrams(&block.text.to_lowercase()) {
+                    let indexes = by_trigram.entry(trigram).or_default();
+                    if indexes.last() != Some(&i) {
+                        indexes.push(i);
+                    }
+                }
+                by_trigram
+            },
+        );
+
+        FileBlocks { blocks, by_trigram }
+    }
+
+    /// The blocks that have all of the trigrams
+    fn candidates<'a>(
+        &'a self,
+        trigrams: &HashSet<Trigram>,
+    ) -> Box<dyn Iterator<Item = &'a MDBlock> + 'a> {
+        let Some(postings) = trigrams
+            .iter()
+            .map(|trigram| self.by_trigram.get(trigram))
+            .collect::<Option<Vec<_>>>()
+        else {
+            return Box::new(std::iter::empty());
+        };
+        let Some(rarest) = postings.iter().min_by_key(|indexes| indexes.len()) else {
+            return Box::new(std::iter::empty());
+        };
+
+        let indexes = rarest
+            .iter()
+            .filter(|i| {
+                postings
+                    .iter()
+                    .all(|indexes| indexes.binary_search(i).is_ok())
+            })
+            .copied()
+            .collect::<Vec<_>>();
+
+        Box::new(indexes.into_iter().flat_map(|i| self.blocks.get(i)))
+    }
+}
+
+/// The blocks that match the pattern, with their scores
+fn fuzzy_match<'a>(pattern: &Pattern, blocks: Vec<Block<'a>>) -> Vec<(Block<'a>, u32)> {
+    blocks
+        .into_par_iter()
+        .map_init(
+            || (Matcher::new(nucleo_matcher::Config::DEFAULT), Vec::new()),
+            |(matcher, buf), block| {
+                let score = pattern.score(Utf32Str::new(block.text, buf), matcher)?;
+                Some((block, score))
+            },
+        )
+        .flatten()
+        .collect()
+}
+
+fn block_of<'a>(path: &'a Path, block: &'a MDBlock) -> Block<'a> {
+    Block {
+        text: &block.text,
+        range: block.range,
+        file: path,
+        kind: block.kind,
+        id: block.id.as_ref().map(|(index, _)| index.as_str()),
+        id_placement: block.id_placement,
+    }
+}
+
+/// The trigrams of the words of the lowercase text; trigrams across whitespace are not indexed, as query words have none
+fn trigrams(text: &str) -> impl Iterator<Item = Trigram> + '_ {
+    text.split_whitespace().flat_map(|word| {
+        let chars = word.chars().collect::<Vec<_>>();
+        chars
+            .windows(3)
+            .map(|window| [window[0], window[1], window[2]])
+            .collect::<Vec<_>>()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use ropey::Rope;
+
+    use super::BlockIndex;
+
+    #[test]
+    fn selects_blocks() {
+        let a = PathBuf::from("/vault/a.md");
+        let b = PathBuf::from("/vault/b.md");
+        let ropes = [
+            (
+                a.clone(),
+                Rope::from_str(
+                    "# Meetings\n\nMeetings this week\n\nWeekly meeting notes ^notes\n\nRoadmap\n\nSteam engine\n",
+                ),
+            ),
+            (b.clone(), Rope::from_str("Meeting with the team")),
+        ];
+        let mut index = BlockIndex::new(
+            ropes
+                .iter()
+                .map(|(path, rope)| (path, rope))
+                .collect::<Vec<_>>(),
+        );
+
+        let texts = |index: &BlockIndex, query: &str| {
+            let mut texts = index
+                .select(query, 10)
+                .into_iter()
+                .map(|block| block.text.to_string())
+                .collect::<Vec<_>>();
+            texts.sort();
+            texts
+        };
+
+        assert_eq!(
+            texts(&index, "meeting"),
+            vec![
+                "Meeting with the team",
+                "Meetings this week",
+                "Weekly meeting notes"
+            ]
+        );
+        assert_eq!(texts(&index, "TEAM meet"), vec!["Meeting with the team"]);
+        assert!(texts(&index, "nothing").is_empty());
+        assert_eq!(index.select("", 2).len(), 2);
+
+        let block = index.select("roadmap", 10)[0];
+        assert_eq!((block.range.start.line, block.range.end.character), (6, 7));
+        assert_eq!(index.select("weekly", 10)[0].id, Some("notes"));
+
+        // the best match of all candidates, a short query and a misspelled one
+        assert_eq!(index.select("team", 1)[0].text, "Meeting with the team");
+        assert_eq!(texts(&index, "ro"), vec!["Roadmap"]);
+        assert_eq!(texts(&index, "wkly notes"), vec!["Weekly meeting notes"]);
+        assert_eq!(texts(&index, "steam engne"), vec!["Steam engine"]);
+        // a fuzzy match that shares less than half of the trigrams of the query is not scored
+        assert!(texts(&index, "sam egin").is_empty());
+
+        index.update(&b, &Rope::from_str("Roadmap review"));
+        assert_eq!(texts(&index, "team"), vec!["Steam engine"]);
+        assert_eq!(texts(&index, "roadmap"), vec!["Roadmap", "Roadmap review"]);
+    }
+}
@@ -0,0 +1,480 @@
+use itertools::Itertools;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use tower_lsp::lsp_types::{Position, Range, TextEdit};
+
+use super::MyRange;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum MDBlockKind {
+    Paragraph,
+    /// With its nested items
+    ListItem,
+    /// Including callouts
+    BlockQuote,
+    Table,
+    CodeBlock,
+}
+
+impl MDBlockKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            MDBlockKind::Paragraph => "Paragraph",
+            MDBlockKind::ListItem => "List Item",
+            MDBlockKind::BlockQuote => "Block Quote",
+            MDBlockKind::Table => "Table",
+            MDBlockKind::CodeBlock => "Code Block",
+        }
+    }
+}
+
+/// Where the `^id` of a block goes, as in Obsidian
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum IdPlacement {
+    /// After the text of paragraphs and list items: `text ^id`. For a list item, this is before its nested items
+    EndOfLine(Position),
+    /// On its own line after block quotes, tables and code blocks, with blank lines around it
+    OwnLine {
+        after: Position,
+        followed_by_text: bool,
+    },
+}
+
+impl IdPlacement {
+    /// The edit that gives the block the id
+    pub fn edit(self, id: &str) -> TextEdit {
+        let (position, new_text) = match self {
+            IdPlacement::EndOfLine(position) => (position, format!("   ^{}", id)),
+            IdPlacement::OwnLine {
+                after,
+                followed_by_text,
+            } => (
+                after,
+                format!("\n\n^{}{}", id, if followed_by_text { "\n" } else { "" }),
+            ),
+        };
+
+        TextEdit {
+            range: Range {
+                start: position,
+                end: position,
+            },
+            new_text,
+        }
+    }
+}
+
+/// A block of a note, which can be linked to by its `^id`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MDBlock {
+    pub kind: MDBlockKind,
+    /// The trimmed lines of the block joined by spaces, without the id. For a list item, only its own lines
+    pub text: String,
+    /// From the start of the first line to the end of the last line
+    pub range: MyRange,
+    /// The id, without the '^', and the range of the id, including the '^'
+    pub id: Option<(String, MyRange)>,
+    pub id_placement: IdPlacement,
+}
+
+static HEADING_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^ {0,3}#{1,6}(?:[ \t]|$)").unwrap());
+static FENCE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[ \t]*(?<fence>`{3,}|~{3,})").unwrap());
+static QUOTE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^ {0,3}>").unwrap());
+static LIST_ITEM_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^[ \t]*(?:[-*+]|\d+[.)])(?:[ \t]|$)").unwrap());
+static TABLE_DELIMITER_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^[ \t]*\|?(?:[ \t]*:?-+:?[ \t]*\|?)+[ \t]*$").unwrap());
+/// An id at the end of a line of a paragraph or list item
+static END_ID_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?:^|\s)(?<id>\^(?<index>[\w-]+))\s*$").unwrap());
+/// The id of the block before, on its own line
+static ID_LINE_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^\s*(?<id>\^(?<index>[\w-]+))\s*$").unwrap());
+
+impl MDBlock {
+    /// The blocks of the text, in order; headings and frontmatter are not blocks. A line that ends with an id ends its block
+    pub fn new(text: &str) -> impl Iterator<Item = MDBlock> {
+        let lines = text.lines().collect::<Vec<_>>();
+        let blank = |i: usize| lines.get(i).is_none_or(|line| line.trim().is_empty());
+        let table_start = |i: usize| {
+            lines[i].contains('|')
+                && lines.get(i + 1).is_some_and(|next| {
+                    next.contains('|') && next.contains('-') && TABLE_DELIMITER_RE.is_match(next)
+                })
+        };
+        let starts_block = |i: usize| {
+            let line = lines[i];
+            HEADING_RE.is_match(line)
+                || FENCE_RE.is_match(line)
+                || QUOTE_RE.is_match(line)
+                || LIST_ITEM_RE.is_match(line)
+                || table_start(i)
+        };
+
+        let mut blocks: Vec<MDBlock> = vec![];
+
+        let mut i = content_start(&lines);
+
+        while i < lines.len() {
+            let line = lines[i];
+
+            if blank(i) || HEADING_RE.is_match(line) {
+                i += 1;
+            } else if let Some(fence) = FENCE_RE.captures(line).and_then(|c| c.name("fence")) {
+                let fence = fence.as_str();
+                let end = (i + 1..lines.len())
+                    .find(|&j| {
+                        let closing = lines[j].trim();
+                        closing.starts_with(fence) && closing.chars().all(|c| fence.starts_with(c))
+                    })
+                    .unwrap_or(lines.len() - 1);
+
+                blocks.push(own_line_block(&lines, MDBlockKind::CodeBlock, i, end));
+                i = end + 1;
+            } else if QUOTE_RE.is_match(line) {
+                let end = last_line_while(&lines, i, |j| QUOTE_RE.is_match(lines[j]));
+
+                blocks.push(own_line_block(&lines, MDBlockKind::BlockQuote, i, end));
+                i = end + 1;
+            } else if table_start(i) {
+                let end = last_line_while(&lines, i, |j| !blank(j) && lines[j].contains('|'));
+
+                blocks.push(own_line_block(&lines, MDBlockKind::Table, i, end));
+                i = end + 1;
+            } else if let Some((full, index)) = ID_LINE_RE
+                .captures(line)
+                .and_then(|c| Some((c.name("id")?, c.name("index")?)))
+            {
+                // the id of the block quote, table or code block just above; an id on its own line after other blocks is not an id
+                let previous = blocks.last_mut().filter(|previous| {
+                    matches!(previous.id_placement, IdPlacement::OwnLine { .. })
+                        && previous.id.is_none()
+                        && (previous.range.end.line as usize + 1..i).all(blank)
+                });
+
+                if let Some(previous) = previous {
+                    previous.id = Some((
+                        index.as_str().to_string(),
+                        line_range(line, i, full.start(), full.end()),
+                    ));
+                }
+                i += 1;
+            } else if LIST_ITEM_RE.is_match(line) {
+                let own_end = last_line_while(&lines, i, |j| {
+                    !blank(j) && !starts_block(j) && !END_ID_RE.is_match(lines[j - 1])
+                });
+
+                let indent = indentation(line);
+                let end = (own_end + 1..lines.len())
+                    .filter(|&j| !blank(j))
+                    .take_while(|&j| indentation(lines[j]) > indent)
+                    .last()
+                    .unwrap_or(own_end);
+
+                blocks.push(end_of_line_block(
+                    &lines,
+                    MDBlockKind::ListItem,
+                    i,
+                    own_end,
+                    end,
+                ));
+                // the nested items are blocks of their own too
+                i = own_end + 1;
+            } else {
+                let end = last_line_while(&lines, i, |j| {
+                    !blank(j) && !starts_block(j) && !END_ID_RE.is_match(lines[j - 1])
+                });
+
+                blocks.push(end_of_line_block(
+                    &lines,
+                    MDBlockKind::Paragraph,
+                    i,
+                    end,
+                    end,
+                ));
+                i = end + 1;
+            }
+        }
+
+        blocks.into_iter()
+    }
+
+    /// The ids of the blocks and headings of the text, in order: the index, without the '^', and the range of the id,
+    /// including the '^'
+    pub fn ids(text: &str) -> impl Iterator<Item = (String, MyRange)> {
+        let lines = text.lines().collect::<Vec<_>>();
+        let blocks = MDBlock::new(text).collect::<Vec<_>>();
+        let in_code_block = |i: usize| {
+            blocks.iter().any(|block| {
+                block.kind == MDBlockKind::CodeBlock
+                    && (block.range.start.line..=block.range.end.line).contains(&(i as u32))
+            })
+        };
+
+        let heading_ids = (content_start(&lines)..lines.len())
+            .filter(|&i| HEADING_RE.is_match(lines[i]) && !in_code_block(i))
+            .flat_map(|i| {
+                let (index, id) = end_id(lines[i])?;
+                Some((index, line_range(lines[i], i, id.start, id.end)))
+            })
+            .collect::<Vec<_>>();
+
+        blocks
+            .into_iter()
+            .flat_map(|block| block.id)
+            .chain(heading_ids)
+            .sorted_by_key(|(_, range)| range.start.line)
+    }
+}
+
+/// The line after the frontmatter
+fn content_start(lines: &[&str]) -> usize {
+    match lines.first().map(|line| line.trim_end()) {
+        Some("---") => (1..lines.len())
+            .find(|&j| matches!(lines[j].trim_end(), "---" | "..."))
+            .map_or(0, |end| end + 1),
+        _ => 0,
+    }
+}
+
+/// The index of the id at the end of the line, without the '^', and the bytes of the id, including the '^'
+fn end_id(line: &str) -> Option<(String, std::ops::Range<usize>)> {
+    let c = END_ID_RE.captures(line)?;
+    let (full, index) = (c.name("id")?, c.name("index")?);
+    Some((index.as_str().to_string(), full.range()))
+}
+
+/// The last line of the lines from `start` on for which the condition holds; the condition is not checked for `start`
+fn last_line_while(lines: &[&str], start: usize, condition: impl Fn(usize) -> bool) -> usize {
+    (start + 1..lines.len())
+        .take_while(|&j| condition(j))
+        .last()
+        .unwrap_or(start)
+}
+
+/// A paragraph or list item, whose id is at the end of its last own line
+fn end_of_line_block(
+    lines: &[&str],
+    kind: MDBlockKind,
+    start: usize,
+    own_end: usize,
+    end: usize,
+) -> MDBlock {
+    let last_line = lines[own_end];
+    let id = end_id(last_line);
+
+    let text = lines[start..own_end]
+        .iter()
+        .copied()
+        .chain([match &id {
+            Some((_, full)) => &last_line[..full.start],
+            None => last_line,
+        }])
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    MDBlock {
+        kind,
+        text,
+        range: lines_range(lines, start, end),
+        id: id.map(|(index, full)| (index, line_range(last_line, own_end, full.start, full.end))),
+        id_placement: IdPlacement::EndOfLine(Position {
+            line: own_end as u32,
+            character: last_line.chars().count() as u32,
+        }),
+    }
+}
+
+/// A block quote, table or code block, whose new id goes on the line after it. An id at the end of the last line
+/// of a block quote or table is read too
+fn own_line_block(lines: &[&str], kind: MDBlockKind, start: usize, end: usize) -> MDBlock {
+    let last_line = lines[end];
+    let id = match kind {
+        MDBlockKind::CodeBlock => None,
+        _ => end_id(last_line),
+    };
+
+    let text = lines[start..end]
+        .iter()
+        .copied()
+        .chain([match &id {
+            Some((_, full)) => &last_line[..full.start],
+            None => last_line,
+        }])
+        .map(|line| match kind {
+            MDBlockKind::BlockQuote => line.trim_start_matches([' ', '\t', '>']).trim(),
+            _ => line.trim(),
+        })
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    let range = lines_range(lines, start, end);
+
+    MDBlock {
+        kind,
+        text,
+        range,
+        id: id.map(|(index, full)| (index, line_range(last_line, end, full.start, full.end))),
+        id_placement: IdPlacement::OwnLine {
+            after: range.end,
+            followed_by_text: lines
+                .get(end + 1)
+                .is_some_and(|next| !next.trim().is_empty()),
+        },
+    }
+}
+
+fn lines_range(lines: &[&str], start: usize, end: usize) -> MyRange {
+    MyRange(Range {
+        start: Position {
+            line: start as u32,
+            character: 0,
+        },
+        end: Position {
+            line: end as u32,
+            character: lines[end].chars().count() as u32,
+        },
+    })
+}
+
+/// The range of the bytes `start..end` of the line
+fn line_range(line: &str, line_nr: usize, start: usize, end: usize) -> MyRange {
+    MyRange(Range {
+        start: Position {
+            line: line_nr as u32,
+            character: line[..start].chars().count() as u32,
+        },
+        end: Position {
+            line: line_nr as u32,
+            character: line[..end].chars().count() as u32,
+        },
+    })
+}
+
+fn indentation(line: &str) -> usize {
+    line.chars().take_while(|c| c.is_whitespace()).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+
+    use super::{IdPlacement, MDBlock, MDBlockKind};
+
+    #[test]
+    fn parses_blocks() {
+        let text = "---\ntitle: blocks\n---\n# Heading\n\nA paragraph\nover two lines ^para\n\n- item\n  continued\n  - nested ^nested\n- second\n\n> [!note] Callout\n> quoted\n\n^quote\n\n| a | b |\n| - | - |\n| 1 | 2 |\nafter the table\n\n```rust\nlet x = 1;\n```";
+
+        let blocks = MDBlock::new(text).collect_vec();
+        let summary = blocks
+            .iter()
+            .map(|block| {
+                (
+                    block.kind,
+                    block.text.as_str(),
+                    block.range.start.line,
+                    block.range.end.line,
+                    block.id.as_ref().map(|(index, _)| index.as_str()),
+                )
+            })
+            .collect_vec();
+
+        assert_eq!(
+            summary,
+            vec![
+                (
+                    MDBlockKind::Paragraph,
+                    "A paragraph over two lines",
+                    5,
+                    6,
+                    Some("para")
+                ),
+                (MDBlockKind::ListItem, "- item continued", 8, 10, None),
+                (MDBlockKind::ListItem, "- nested", 10, 10, Some("nested")),
+                (MDBlockKind::ListItem, "- second", 11, 11, None),
+                (
+                    MDBlockKind::BlockQuote,
+                    "[!note] Callout quoted",
+                    13,
+                    14,
+                    Some("quote")
+                ),
+                (
+                    MDBlockKind::Table,
+                    "| a | b | | - | - | | 1 | 2 |",
+                    18,
+                    20,
+                    None
+                ),
+                (MDBlockKind::Paragraph, "after the table", 21, 21, None),
+                (
+                    MDBlockKind::CodeBlock,
+                    "```rust let x = 1; ```",
+                    23,
+                    25,
+                    None
+                ),
+            ]
+        );
+
+        let (_, id_range) = blocks[0].id.as_ref().unwrap();
+        assert_eq!((id_range.start.character, id_range.end.character), (15, 20));
+        let (_, id_range) = blocks[4].id.as_ref().unwrap();
+        assert_eq!((id_range.start.line, id_range.start.character), (16, 0));
+
+        assert_eq!(
+            blocks[1].id_placement.edit("abc").range.start,
+            tower_lsp::lsp_types::Position {
+                line: 9,
+                character: 11
+            }
+        );
+        assert_eq!(blocks[5].id_placement.edit("abc").new_text, "\n\n^abc\n");
+        assert!(matches!(
+            blocks[7].id_placement,
+            IdPlacement::OwnLine {
+                followed_by_text: false,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn reads_trailing_ids() {
+        let text = "# Heading ^heading\n\n> quoted\n> text ^quote\n\n| a | b |\n| - | - |\n| 1 | 2 | ^table\n\n```\n# not a heading ^code\n```";
+
+        let blocks = MDBlock::new(text).collect_vec();
+        let summary = blocks
+            .iter()
+            .map(|block| {
+                (
+                    block.text.as_str(),
+                    block.id.as_ref().map(|(index, _)| index.as_str()),
+                )
+            })
+            .collect_vec();
+        assert_eq!(
+            summary,
+            vec![
+                ("quoted text", Some("quote")),
+                ("| a | b | | - | - | | 1 | 2 |", Some("table")),
+                ("``` # not a heading ^code ```", None),
+            ]
+        );
+
+        let ids = MDBlock::ids(text)
+            .map(|(index, range)| (index, range.start.line, range.start.character))
+            .collect_vec();
+        assert_eq!(
+            ids,
+            vec![
+                ("heading".to_string(), 0, 10),
+                ("quote".to_string(), 3, 7),
+                ("table".to_string(), 7, 10),
+            ]
+        );
+    }
+}
@@ -1,9 +1,13 @@
+mod block_index;
+mod blocks;
 mod metadata;
 mod parsing;
+mod task;
 
 use std::{
     char,
     collections::{HashMap, HashSet},
+    ffi::OsStr,
     hash::Hash,
     iter,
     ops::{Deref, DerefMut, Not, Range},
@@ -23,7 +27,7 @@ use walkdir::WalkDir;
 
 impl Vault {
     pub fn construct_vault(context: &Settings, root_dir: &Path) -> Result<Vault, std::io::Error> {
-        let md_file_paths = WalkDir::new(root_dir)
+        let (md_file_paths, attachment_paths): (Vec<_>, Vec<_>) = WalkDir::new(root_dir)
             .into_iter()
             .filter_entry(|e| {
                 !e.file_name()
@@ -32,8 +36,8 @@ impl Vault {
                     .unwrap_or(false)
             })
             .flatten()
-            .filter(|f| f.path().extension().and_then(|e| e.to_str()) == Some("md"))
-            .collect_vec();
+            .filter(|f| f.file_type().is_file())
+            .partition(|f| f.path().extension().and_then(|e| e.to_str()) == Some("md"));
 
         let md_files: HashMap<PathBuf, MDFile> = md_file_paths
             .par_iter()
@@ -41,7 +45,7 @@ impl Vault {
                 let text = std::fs::read_to_string(p.path())?;
                 let md_file = MDFile::new(context, &text, PathBuf::from(p.path()));
 
-                return Ok::<(PathBuf, MDFile), std::io::Error>((p.path().into(), md_file));
+                Ok::<(PathBuf, MDFile), std::io::Error>((p.path().into(), md_file))
             })
             .collect();
 
@@ -51,30 +55,85 @@ impl Vault {
                 let text = std::fs::read_to_string(p.path())?;
                 let rope = Rope::from_str(&text);
 
-                return Ok::<(PathBuf, Rope), std::io::Error>((p.path().into(), rope));
+                Ok::<(PathBuf, Rope), std::io::Error>((p.path().into(), rope))
             })
             .collect();
 
+        let block_index = BlockIndex::new(&ropes);
+        let file_names = FileNames::new(root_dir, md_files.keys());
+        let backlink_counts = md_files
+            .values()
+            .flat_map(MDFile::linked_file_names)
+            .counts();
+
+        let modified_times = md_file_paths
+            .iter()
+            .flat_map(|p| Some((p.path().into(), p.metadata().ok()?.modified().ok()?)))
+            .collect();
+
         Ok(Vault {
             ropes: ropes.into(),
             md_files: md_files.into(),
+            block_index,
+            file_names,
+            backlink_counts,
+            attachments: attachment_paths
+                .into_iter()
+                .map(|entry| entry.into_path())
+                .collect(),
+            modified_times,
             root_dir: root_dir.into(),
         })
     }
 
+    /// A vault of the texts, by their paths relative to the root, without reading the file system
+    #[cfg(test)]
+    pub fn from_texts(context: &Settings, root_dir: &Path, files: &[(&str, &str)]) -> Vault {
+        let mut vault = Vault {
+            ropes: HashMap::new().into(),
+            md_files: HashMap::new().into(),
+            block_index: BlockIndex::new(&HashMap::new()),
+            file_names: FileNames::default(),
+            backlink_counts: HashMap::new(),
+            attachments: Vec::new(),
+            modified_times: HashMap::new(),
+            root_dir: root_dir.into(),
+        };
+
+        for (path, text) in files {
+            Vault::update_vault(context, &mut vault, (&root_dir.join(path), text));
+        }
+
+        vault
+    }
+
     pub fn update_vault(context: &Settings, old: &mut Vault, new_file: (&PathBuf, &str)) {
         let new_md_file = MDFile::new(context, new_file.1, new_file.0.clone());
+        for file_name in new_md_file.linked_file_names() {
+            *old.backlink_counts.entry(file_name).or_default() += 1;
+        }
+
         let new = old.md_files.get_mut(new_file.0);
         match new {
             Some(file) => {
+                for file_name in file.linked_file_names() {
+                    if let Some(count) = old.backlink_counts.get_mut(&file_name) {
+                        *count -= 1;
+                        if *count == 0 {
+                            old.backlink_counts.remove(&file_name);
+                        }
+                    }
+                }
                 *file = new_md_file;
             }
             None => {
                 old.md_files.insert(new_file.0.into(), new_md_file);
+                old.file_names.insert(&old.root_dir, new_file.0);
             }
         };
 
         let new_rope = Rope::from_str(new_file.1);
+        old.block_index.update(new_file.0, &new_rope);
         let rope_entry = old.ropes.get_mut(new_file.0);
 
         match rope_entry {
@@ -85,6 +144,24 @@ impl Vault {
                 old.ropes.insert(new_file.0.into(), new_rope);
             }
         }
+
+        // the file is being edited, so it is modified now even if the change is not saved
+        old.modified_times
+            .insert(new_file.0.into(), SystemTime::now());
+    }
+
+    /// Track the attachments that were created, renamed or deleted since the vault was constructed; markdown files
+    /// are not attachments
+    pub fn update_attachments(&mut self, removed: &[PathBuf], added: &[PathBuf]) {
+        self.attachments.retain(|path| !removed.contains(path));
+
+        let added = added
+            .iter()
+            .filter(|path| path.extension().and_then(|ext| ext.to_str()) != Some("md"))
+            .filter(|path| !self.attachments.contains(path))
+            .cloned()
+            .collect_vec();
+        self.attachments.extend(added);
     }
 }
 
@@ -134,6 +211,15 @@ impl Hash for Vault {
 pub struct Vault {
     pub md_files: MyHashMap<MDFile>,
     pub ropes: MyHashMap<Rope>,
+    block_index: BlockIndex,
+    /// The names of the markdown files, to link to them by their shortest unique path
+    file_names: FileNames,
+    /// Number of links to each file, by lowercase file name
+    backlink_counts: HashMap<String, usize>,
+    /// The files of the vault that are not markdown, listed when the vault is constructed
+    attachments: Vec<PathBuf>,
+    /// The modification times of the markdown files, read when the vault is constructed and set when a file is updated
+    modified_times: HashMap<PathBuf, SystemTime>,
     root_dir: PathBuf,
 }
 
@@ -193,7 +279,7 @@ impl Vault {
         &'a self,
         path: &'a Path,
         position: Position,
-    ) -> Option<&Reference> {
+    ) -> Option<&'a Reference> {
         let links = self.select_references(Some(path))?;
 
         let (_path, reference) = links.into_iter().find(|&l| {
@@ -358,7 +444,7 @@ impl Vault {
         &self,
         reference: &Reference,
         reference_path: &Path,
-    ) -> Vec<Referenceable> {
+    ) -> Vec<Referenceable<'_>> {
         let referenceables = self.select_referenceable_nodes(None);
 
         referenceables
@@ -434,35 +520,41 @@ impl Vault {
         }
     }
 
-    pub fn select_blocks(&self) -> Vec<Block<'_>> {
-        self.ropes
-            .par_iter()
-            .map(|(path, rope)| {
-                rope.lines()
-                    .enumerate()
-                    .flat_map(|(i, line)| {
-                        let string = line.as_str()?;
-
-                        Some(Block {
-                            text: string.trim(),
-                            range: MyRange(tower_lsp::lsp_types::Range {
-                                start: Position {
-                                    line: i as u32,
-                                    character: 0,
-                                },
-                                end: Position {
-                                    line: i as u32,
-                                    character: string.len() as u32,
-                                },
-                            }),
-                            file: path,
-                        })
-                    })
-                    .collect::<Vec<_>>()
-            })
-            .flatten()
-            .filter(|block| !block.text.is_empty())
-            .collect()
+    /// At most `limit` blocks that fuzzy match the query, best first, from the block index
+    pub fn select_blocks(&self, query: &str, limit: usize) -> Vec<Block<'_>> {
+        self.block_index.select(query, limit)
+    }
+
+    /// The block of the file with the id, without the '^'
+    pub fn select_block(&self, path: &Path, index: &str) -> Option<Block<'_>> {
+        self.block_index.block(path, index)
+    }
+
+    /// The names of the markdown files of the vault
+    pub fn select_file_names(&self) -> &FileNames {
+        &self.file_names
+    }
+
+    /// The number of links to the markdown file at the path, by its file name
+    pub fn select_backlink_count(&self, path: &Path) -> usize {
+        path.file_stem()
+            .and_then(|stem| stem.to_str())
+            .and_then(|stem| self.backlink_counts.get(&link_file_name(stem)))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// The modification time of a markdown file of the vault
+    pub fn select_modified_time(&self, path: &Path) -> Option<SystemTime> {
+        self.modified_times.get(path).copied()
+    }
+
+    /// A file of the vault that is not markdown, by its file name
+    pub fn select_attachment(&self, file_name: &OsStr) -> Option<&Path> {
+        self.attachments
+            .iter()
+            .find(|path| path.file_name() == Some(file_name))
+            .map(PathBuf::as_path)
     }
 }
 
@@ -471,6 +563,10 @@ pub struct Block<'a> {
     pub text: &'a str,
     pub range: MyRange,
     pub file: &'a Path,
+    pub kind: MDBlockKind,
+    /// The id that the block already has, without the '^'
+    pub id: Option<&'a str>,
+    pub id_placement: IdPlacement,
 }
 
 impl AsRef<str> for Block<'_> {
@@ -549,6 +645,17 @@ pub struct MDFile {
     pub link_reference_definitions: Vec<MDLinkReferenceDefinition>,
     pub metadata: Option<MDMetadata>,
     pub codeblocks: Vec<MDCodeBlock>,
+    pub tasks: Vec<MDTask>,
+    /// Links to urls and attachments, outside of code blocks
+    pub external_links: Vec<MDExternalLink>,
+}
+
+/// Links are matched by file name, as in `[[folder/Note]]`, `[[Note.md]]` or `[Note](folder/Note%20Name.md)`
+fn link_file_name(link: &str) -> String {
+    let file_name = link.rsplit('/').next().unwrap_or(link);
+    let file_name = file_name.strip_suffix(".md").unwrap_or(file_name);
+
+    file_name.replace("%20", " ").to_lowercase()
 }
 
 impl MDFile {
@@ -582,6 +689,12 @@ impl MDFile {
             _ => MDTag::new(text).collect_vec(),
         };
         let metadata = MDMetadata::new(text);
+        let tasks = MDTask::new(text)
+            .filter(|it| !code_blocks.iter().any(|codeblock| codeblock.includes(it)))
+            .collect_vec();
+        let external_links = MDExternalLink::new(text)
+            .filter(|it| !code_blocks.iter().any(|codeblock| codeblock.includes(it)))
+            .collect_vec();
 
         MDFile {
             references: links,
@@ -593,16 +706,31 @@ impl MDFile {
             link_reference_definitions: link_refs.collect(),
             metadata,
             codeblocks: code_blocks,
+            tasks,
+            external_links,
         }
     }
-
-    pub fn file_name(&self) -> Option<&str> {
-        self.path.file_stem()?.to_str()
-    }
 }
 
 impl MDFile {
-    fn get_referenceables(&self) -> Vec<Referenceable> {
+    /// The lowercase file names that the links of the file link to, once for each link
+    fn linked_file_names(&self) -> impl Iterator<Item = String> + '_ {
+        self.references
+            .iter()
+            .flat_map(|reference| match reference {
+                Reference::WikiFileLink(data) | Reference::MDFileLink(data) => {
+                    Some(data.reference_text.as_str())
+                }
+                Reference::WikiHeadingLink(_, file, _)
+                | Reference::WikiIndexedBlockLink(_, file, _)
+                | Reference::MDHeadingLink(_, file, _)
+                | Reference::MDIndexedBlockLink(_, file, _) => Some(file.as_str()),
+                Reference::Tag(..) | Reference::Footnote(..) | Reference::LinkRef(..) => None,
+            })
+            .map(link_file_name)
+    }
+
+    fn get_referenceables(&self) -> Vec<Referenceable<'_>> {
         let MDFile {
             references: _,
             headings,
@@ -613,6 +741,8 @@ impl MDFile {
             link_reference_definitions,
             metadata: _,
             codeblocks: _,
+            tasks: _,
+            external_links: _,
         } = self;
 
         iter::once(Referenceable::File(&self.path, self))
@@ -686,9 +816,27 @@ impl Default for Reference {
 
 use Reference::*;
 
-use crate::config::Settings;
+use crate::{
+    config::Settings,
+    link_format::{folder_ref_path, matches_link_path, FileNames},
+};
+
+pub use self::blocks::{IdPlacement, MDBlockKind};
+pub use self::task::{MDTask, TaskStatus};
+use self::{block_index::BlockIndex, blocks::MDBlock, metadata::MDMetadata, parsing::MDCodeBlock};
 
-use self::{metadata::MDMetadata, parsing::MDCodeBlock};
+static WIKI_LINK_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"\[\[(?<filepath>(\.\.?\/)*[^\[\]\|\.\#]+)?(\#(?<infileref>[^\[\]\.\|]+))?(?<ending>\.[^\# <>]+)?(\|(?<display>[^\[\]\.\|]+))?\]\]")
+        .unwrap()
+}); // A [[link]] that does not have any [ or ] in it
+
+static MD_LINK_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"\[(?<display>[^\[\]\.]*)\]\(<?(?<filepath>(\.\.?\/)*[^\[\]\|\.\#<>]+)?(?<ending>\.[^\# <>]+)?(\#(?<infileref>[^\[\]\.\|<>]+))?>?\)")
+        .expect("MD Link Not Constructing")
+}); // [display](relativePath)
+
+static AUTOLINK_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"<(?<target>(https?|mailto):[^<>\s]+)>").unwrap()); // <https://url>
 
 impl Reference {
     pub fn data(&self) -> &ReferenceData {
@@ -720,38 +868,27 @@ impl Reference {
     }
 
     pub fn new<'a>(text: &'a str, file_name: &'a str) -> impl Iterator<Item = Reference> + 'a {
-        static WIKI_LINK_RE: Lazy<Regex> = Lazy::new(|| {
-            Regex::new(r"\[\[(?<filepath>[^\[\]\|\.\#]+)?(\#(?<infileref>[^\[\]\.\|]+))?(?<ending>\.[^\# <>]+)?(\|(?<display>[^\[\]\.\|]+))?\]\]")
-
-                .unwrap()
-        }); // A [[link]] that does not have any [ or ] in it
-
         let wiki_links = WIKI_LINK_RE
             .captures_iter(text)
-            .filter(
-                |captures| match captures.name("ending").map(|ending| ending.as_str()) {
-                    Some(".md") | None => true,
-                    _ => false,
-                },
-            )
+            .filter(|captures| {
+                matches!(
+                    captures.name("ending").map(|ending| ending.as_str()),
+                    Some(".md") | None
+                )
+            })
             .flat_map(RegexTuple::new)
             .flat_map(|regextuple| {
                 generic_link_constructor::<WikiReferenceConstructor>(text, file_name, regextuple)
             });
 
-        static MD_LINK_RE: Lazy<Regex> = Lazy::new(|| {
-            Regex::new(r"\[(?<display>[^\[\]\.]*)\]\(<?(?<filepath>(\.?\/)?[^\[\]\|\.\#<>]+)?(?<ending>\.[^\# <>]+)?(\#(?<infileref>[^\[\]\.\|<>]+))?>?\)")
-                .expect("MD Link Not Constructing")
-        }); // [display](relativePath)
-
         let md_links = MD_LINK_RE
             .captures_iter(text)
-            .filter(
-                |captures| match captures.name("ending").map(|ending| ending.as_str()) {
-                    Some(".md") | None => true,
-                    _ => false,
-                },
-            )
+            .filter(|captures| {
+                matches!(
+                    captures.name("ending").map(|ending| ending.as_str()),
+                    Some(".md") | None
+                )
+            })
             .flat_map(RegexTuple::new)
             .flat_map(|regextuple| {
                 generic_link_constructor::<MDReferenceConstructor>(text, file_name, regextuple)
@@ -875,7 +1012,12 @@ impl Reference {
                 | WikiFileLink(ReferenceData {
                     reference_text: file_ref_text,
                     ..
-                }) => matches_path_or_file(file_ref_text, referenceable.get_refname(root_dir)),
+                }) => matches_path_or_file(
+                    file_ref_text,
+                    referenceable.get_refname(root_dir),
+                    root_dir,
+                    file_path,
+                ),
                 Tag(_) => false,
                 WikiHeadingLink(_, _, _) => false,
                 WikiIndexedBlockLink(_, _, _) => false,
@@ -903,8 +1045,13 @@ impl Reference {
                 | WikiIndexedBlockLink(.., file_ref_text, link_infile_ref)
                 | MDHeadingLink(.., file_ref_text, link_infile_ref)
                 | MDIndexedBlockLink(.., file_ref_text, link_infile_ref) => {
-                    matches_path_or_file(file_ref_text, referenceable.get_refname(root_dir))
-                        && link_infile_ref.to_lowercase() == infile_ref.to_lowercase()
+                    matches_path_or_file(
+                        file_ref_text,
+                        referenceable.get_refname(root_dir),
+                        root_dir,
+                        file_path,
+                    ) && link_infile_ref.replace("%20", " ").to_lowercase()
+                        == infile_ref.to_lowercase()
                 }
                 Tag(_) => false,
                 WikiFileLink(_) => false,
@@ -1127,12 +1274,10 @@ impl MDHeading {
                     _ => None,
                 },
             )
-            .map(|(full_heading, heading_match, starter)| {
-                return MDHeading {
-                    heading_text: heading_match.as_str().trim_end().into(),
-                    range: MyRange::from_range(&Rope::from_str(text), full_heading.range()),
-                    level: HeadingLevel(starter.as_str().len()),
-                };
+            .map(|(full_heading, heading_match, starter)| MDHeading {
+                heading_text: heading_match.as_str().trim_end().into(),
+                range: MyRange::from_range(&Rope::from_str(text), full_heading.range()),
+                level: HeadingLevel(starter.as_str().len()),
             });
 
         headings
@@ -1153,23 +1298,11 @@ impl Hash for MDIndexedBlock {
 }
 
 impl MDIndexedBlock {
+    /// The ids of the blocks of the text, where Obsidian expects them: at the end of paragraphs, list items, headings
+    /// and the last line of block quotes and tables, and on their own line after block quotes, tables and code blocks
     fn new(text: &str) -> impl Iterator<Item = MDIndexedBlock> + '_ {
-        static INDEXED_BLOCK_RE: Lazy<Regex> =
-            Lazy::new(|| Regex::new(r".+ (\^(?<index>\w+))").unwrap());
-
-        let indexed_blocks = INDEXED_BLOCK_RE
-            .captures_iter(text)
-            .flat_map(|c| match (c.get(1), c.name("index")) {
-                (Some(full), Some(index)) => Some((full, index)),
-                _ => None,
-            })
-            .map(|(full, index)| MDIndexedBlock {
-                index: index.as_str().into(),
-                range: MyRange::from_range(&Rope::from_str(text), full.range()),
-            });
-
-        indexed_blocks
-    } // Make this better identify the full blocks
+        MDBlock::ids(text).map(|(index, range)| MDIndexedBlock { index, range })
+    }
 }
 
 #[derive(Debug, Eq, PartialEq, Clone)]
@@ -1240,7 +1373,79 @@ impl MDTag {
                 range: MyRange::from_range(&Rope::from_str(text), full.range()),
             });
 
-        tagged_blocks
+        tagged_blocks.chain(MDTag::new_frontmatter(text))
+    }
+
+    /// Tags in the `tags` property of the frontmatter, either inline (`tags: [a, b/c]`) or as a yaml list.
+    /// These are written without the '#', so their ranges do not include it
+    fn new_frontmatter(text: &str) -> impl Iterator<Item = MDTag> {
+        static FRONTMATTER_RE: Lazy<Regex> =
+            Lazy::new(|| Regex::new(r"^---\r?\n(?<metadata>(\n|.)*?)\r?\n---").unwrap());
+        static TAGS_KEY_RE: Lazy<Regex> =
+            Lazy::new(|| Regex::new(r"^tags?:(?<inline>.*)$").unwrap());
+        static LIST_ITEM_RE: Lazy<Regex> =
+            Lazy::new(|| Regex::new(r"^\s*-\s+(?<item>.*)$").unwrap());
+        static TAG_TEXT_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"[a-zA-Z0-9_\-\/]+").unwrap());
+
+        let Some(metadata) = FRONTMATTER_RE
+            .captures(text)
+            .and_then(|captures| captures.name("metadata"))
+        else {
+            return vec![].into_iter();
+        };
+
+        // (byte offset in the text, text that may contain tags); the '\r' of CRLF line endings is not part of the line
+        let lines = metadata
+            .as_str()
+            .split('\n')
+            .scan(metadata.start(), |offset, line| {
+                let start = *offset;
+                *offset += line.len() + 1;
+                Some((start, line.strip_suffix('\r').unwrap_or(line)))
+            });
+
+        let mut tag_texts = vec![];
+        let mut in_tags_list = false;
+        for (offset, line) in lines {
+            if let Some(inline) = TAGS_KEY_RE.captures(line).and_then(|c| c.name("inline")) {
+                tag_texts.push((offset + inline.start(), inline.as_str()));
+                in_tags_list = inline.as_str().trim().is_empty();
+            } else if let (true, Some(item)) = (
+                in_tags_list,
+                LIST_ITEM_RE.captures(line).and_then(|c| c.name("item")),
+            ) {
+                tag_texts.push((offset + item.start(), item.as_str()));
+            } else {
+                in_tags_list = false;
+            }
+        }
+
+        let rope = Rope::from_str(text);
+
+        tag_texts
+            .into_iter()
+            .flat_map(|(offset, tag_text)| {
+                TAG_TEXT_RE
+                    .find_iter(tag_text)
+                    .map(move |tag| (offset + tag.start(), tag.as_str()))
+                    .collect_vec()
+            })
+            .filter(|(_, tag)| tag.chars().next().is_some_and(|c| !c.is_numeric()))
+            .filter(|(_, tag)| tag.chars().any(|c| c.is_alphabetic()))
+            .map(|(start, tag)| MDTag {
+                tag_ref: tag.into(),
+                range: MyRange::from_range(&rope, start..start + tag.len()),
+            })
+            .collect_vec()
+            .into_iter()
+    }
+
+    /// The tag and each of its parents; `a/b/c` is `a`, `a/b` and `a/b/c`
+    pub fn tag_paths(tag_ref: &str) -> impl Iterator<Item = &str> {
+        tag_ref
+            .match_indices('/')
+            .map(|(i, _)| &tag_ref[..i])
+            .chain(std::iter::once(tag_ref))
     }
 }
 
@@ -1276,6 +1481,78 @@ impl MDLinkReferenceDefinition {
     }
 }
 
+/// A link to a website or an attachment; these are not references to the vault
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct MDExternalLink {
+    pub range: MyRange,
+    /// The url or the path of the attachment, with its fragment
+    pub target: String,
+    /// true for links to a url, such as https://..., rather than to a file
+    pub is_url: bool,
+}
+
+impl Rangeable for MDExternalLink {
+    fn range(&self) -> &MyRange {
+        &self.range
+    }
+}
+
+impl MDExternalLink {
+    /// The wiki and markdown links of the text to anything but notes, and the autolinks
+    fn new(text: &str) -> impl Iterator<Item = MDExternalLink> + '_ {
+        let rope = Rope::from_str(text);
+
+        let links = WIKI_LINK_RE
+            .captures_iter(text)
+            .chain(MD_LINK_RE.captures_iter(text))
+            .flat_map(|captures| {
+                // the ending of a wikilink runs into its display text: [[image.png|200]]
+                let ending = captures.name("ending")?.as_str().split('|').next()?;
+                if ending == ".md" {
+                    return None;
+                }
+
+                let target = format!(
+                    "{}{}{}",
+                    captures.name("filepath").map_or("", |path| path.as_str()),
+                    ending,
+                    captures
+                        .name("infileref")
+                        .map_or(String::new(), |infileref| format!(
+                            "#{}",
+                            infileref.as_str()
+                        ))
+                );
+
+                Some((captures.get(0)?, target))
+            });
+        let autolinks = AUTOLINK_RE.captures_iter(text).flat_map(|captures| {
+            Some((
+                captures.get(0)?,
+                captures.name("target")?.as_str().to_string(),
+            ))
+        });
+
+        links
+            .chain(autolinks)
+            .flat_map(|(full, target)| {
+                let target = target.trim();
+                let is_url = is_url(target);
+
+                (is_url || !target.starts_with("data:")).then(|| MDExternalLink {
+                    range: MyRange::from_range(&rope, full.range()),
+                    target: target.to_string(),
+                    is_url,
+                })
+            })
+            .sorted_by_key(|link| (link.range.start.line, link.range.start.character))
+    }
+}
+
+pub fn is_url(target: &str) -> bool {
+    target.starts_with("http://") || target.starts_with("https://") || target.starts_with("mailto:")
+}
+
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 /**
 An Algebreic type for methods for all referenceables, which are anything able to be referenced through obsidian link or tag. These include
@@ -1315,7 +1592,7 @@ impl Refname {
     pub fn link_file_key(&self) -> Option<String> {
         let path = &self.path.clone()?;
 
-        let last = path.split('/').last()?;
+        let last = path.split('/').next_back()?;
 
         Some(last.to_string())
     }
@@ -1348,7 +1625,6 @@ impl From<&str> for Refname {
 
 impl Referenceable<'_> {
     /// Gets the generic reference name for a referenceable. This will not include any display text. If trying to determine if text is a reference of a particular referenceable, use the `is_reference` function
-
     pub fn get_refname(&self, root_dir: &Path) -> Option<Refname> {
         match self {
             Referenceable::File(path, _) => {
@@ -1431,7 +1707,7 @@ impl Referenceable<'_> {
                         let refname_split = refname.split('/').collect_vec();
                         let text_split = text.split('/').collect_vec();
 
-                        return text_split.get(0..refname_split.len()) == Some(&refname_split);
+                        text_split.get(0..refname_split.len()) == Some(&refname_split)
                     })
             }
             Referenceable::Footnote(path, _footnote) => match reference {
@@ -1460,9 +1736,12 @@ impl Referenceable<'_> {
                     ..
                 })
                 | MDHeadingLink(.., file_ref_text, _)
-                | MDIndexedBlockLink(.., file_ref_text, _) => {
-                    matches_path_or_file(file_ref_text, self.get_refname(root_dir))
-                }
+                | MDIndexedBlockLink(.., file_ref_text, _) => matches_path_or_file(
+                    file_ref_text,
+                    self.get_refname(root_dir),
+                    root_dir,
+                    reference_path,
+                ),
                 Tag(_) => false,
                 Footnote(_) => false,
                 LinkRef(_) => false,
@@ -1510,22 +1789,29 @@ impl Referenceable<'_> {
     }
 }
 
-fn matches_path_or_file(file_ref_text: &str, refname: Option<Refname>) -> bool {
+/// The path of the linking file is needed for relative links, as in `../project/meeting`
+fn matches_path_or_file(
+    file_ref_text: &str,
+    refname: Option<Refname>,
+    root_dir: &Path,
+    reference_path: &Path,
+) -> bool {
     (|| {
         let refname = refname?;
         let refname_path = refname.path.clone()?; // this function should not be used for tags, ... only for heading, files, indexed blocks
 
-        if file_ref_text.contains('/') {
-            let file_ref_text = file_ref_text.replace(r"%20", " ");
-            let file_ref_text = file_ref_text.replace(r"\ ", " ");
+        let file_ref_text = file_ref_text.replace(r"%20", " ");
+        let file_ref_text = file_ref_text.replace(r"\ ", " ");
 
-            let chars: Vec<char> = file_ref_text.chars().collect();
-            match chars.as_slice() {
-                &['.', '/', ref path @ ..] | &['/', ref path @ ..] => {
-                    Some(String::from_iter(path) == refname_path)
-                }
-                path => Some(String::from_iter(path) == refname_path),
+        if file_ref_text.contains('/') {
+            // unresolved references have the link path as their refname
+            if file_ref_text == refname_path {
+                return Some(true);
             }
+
+            let from_dir = folder_ref_path(root_dir, reference_path).unwrap_or_default();
+
+            Some(matches_link_path(&refname_path, &file_ref_text, &from_dir))
         } else {
             let last_segment = refname.link_file_key()?;
 
@@ -1538,7 +1824,7 @@ fn matches_path_or_file(file_ref_text: &str, refname: Option<Refname>) -> bool {
 // tests
 #[cfg(test)]
 mod vault_tests {
-    use std::path::Path;
+    use std::{ffi::OsStr, path::Path};
 
     use itertools::Itertools;
     use tower_lsp::lsp_types::{Position, Range};
@@ -1547,7 +1833,10 @@ mod vault_tests {
     use crate::vault::{MDLinkReferenceDefinition, Refname};
 
     use super::Reference::*;
-    use super::{MDFile, MDFootnote, MDHeading, MDIndexedBlock, MDTag, Reference, Referenceable};
+    use super::{
+        link_file_name, MDExternalLink, MDFile, MDFootnote, MDHeading, MDIndexedBlock, MDTag,
+        Reference, Referenceable, Settings, Vault,
+    };
 
     #[test]
     fn wiki_link_parsing() {
@@ -2039,6 +2328,79 @@ mod vault_tests {
         assert_eq!(parsed, vec![])
     }
 
+    #[test]
+    fn external_link_parsing() {
+        let text = "[site](https://example.com) [[note]] [[image.png|200]]\n[doc](<my file.pdf>) <https://auto.link> [other](note.md)";
+        let parsed = MDExternalLink::new(text)
+            .map(|link| {
+                (
+                    link.range.start.line,
+                    link.range.start.character,
+                    link.range.end.character,
+                    link.target,
+                    link.is_url,
+                )
+            })
+            .collect_vec();
+
+        assert_eq!(
+            parsed,
+            vec![
+                (0, 0, 27, "https://example.com".to_string(), true),
+                (0, 37, 54, "image.png".to_string(), false),
+                (1, 0, 20, "my file.pdf".to_string(), false),
+                (1, 21, 40, "https://auto.link".to_string(), true),
+            ]
+        )
+    }
+
+    #[test]
+    fn updates_backlink_counts() {
+        assert_eq!(link_file_name("folder/Note%20Name.md"), "note name");
+        assert_eq!(link_file_name("Note"), "note");
+
+        let root_dir = Path::new("/vault");
+        let settings = Settings::for_vault(root_dir);
+        let mut vault = Vault::from_texts(
+            &settings,
+            root_dir,
+            &[
+                ("a.md", "[[Target]] [[target#Heading]] #tag"),
+                ("b.md", "[target](folder/target.md)"),
+            ],
+        );
+        let target = root_dir.join("folder/target.md");
+        assert_eq!(vault.select_backlink_count(&target), 3);
+
+        Vault::update_vault(&settings, &mut vault, (&root_dir.join("a.md"), "[[other]]"));
+        assert_eq!(vault.select_backlink_count(&target), 1);
+        assert_eq!(vault.select_backlink_count(&root_dir.join("other.md")), 1);
+        assert!(vault.select_modified_time(&root_dir.join("a.md")).is_some());
+    }
+
+    #[test]
+    fn updates_attachments() {
+        let root_dir = Path::new("/vault");
+        let settings = Settings::for_vault(root_dir);
+        let mut vault = Vault::from_texts(&settings, root_dir, &[("note.md", "![[image.png]]")]);
+        let image = root_dir.join("assets/image.png");
+        let renamed = root_dir.join("assets/photo.png");
+
+        vault.update_attachments(&[], &[image.clone(), root_dir.join("other.md")]);
+        assert_eq!(
+            vault.select_attachment(OsStr::new("image.png")),
+            Some(image.as_path())
+        );
+        assert_eq!(vault.select_attachment(OsStr::new("other.md")), None);
+
+        vault.update_attachments(&[image], std::slice::from_ref(&renamed));
+        assert_eq!(vault.select_attachment(OsStr::new("image.png")), None);
+        assert_eq!(
+            vault.select_attachment(OsStr::new("photo.png")),
+            Some(renamed.as_path())
+        );
+    }
+
     #[test]
     fn heading_parsing() {
         let text = r"# This is a heading
@@ -2489,6 +2851,38 @@ Continued
         assert_eq!(expected, parsed)
     }
 
+    #[test]
+    fn frontmatter_tags() {
+        let text = "---\ntags: [project/alpha, \"daily\"]\naliases:\n  - not a tag\ntag:\n  - area/work\n---\n#inline";
+
+        let parsed = MDTag::new(text).collect_vec();
+        let tag = |tag_ref: &str, line, start, end| MDTag {
+            tag_ref: tag_ref.into(),
+            range: MyRange(Range {
+                start: Position {
+                    line,
+                    character: start,
+                },
+                end: Position {
+                    line,
+                    character: end,
+                },
+            }),
+        };
+
+        let expected = vec![
+            tag("inline", 7, 0, 7),
+            tag("project/alpha", 1, 7, 20),
+            tag("daily", 1, 23, 28),
+            tag("area/work", 5, 4, 13),
+        ];
+
+        assert_eq!(expected, parsed);
+
+        let crlf_text = text.replace('\n', "\r\n");
+        assert_eq!(expected, MDTag::new(&crlf_text).collect_vec());
+    }
+
     #[test]
     fn tag_starting_with_number_no_tag() {
         let text = "not a tag #7";
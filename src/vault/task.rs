@@ -0,0 +1,186 @@
+use chrono::NaiveDate;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use ropey::Rope;
+
+use super::{MyRange, Rangeable};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TaskStatus {
+    /// `[ ]`
+    Open,
+    /// `[x]`
+    Done,
+    /// `[/]`
+    InProgress,
+    /// `[-]`
+    Cancelled,
+    /// `[>]`
+    Deferred,
+    Other(char),
+}
+
+impl TaskStatus {
+    pub fn from_char(c: char) -> TaskStatus {
+        match c {
+            ' ' => TaskStatus::Open,
+            'x' | 'X' => TaskStatus::Done,
+            '/' => TaskStatus::InProgress,
+            '-' => TaskStatus::Cancelled,
+            '>' => TaskStatus::Deferred,
+            other => TaskStatus::Other(other),
+        }
+    }
+
+    pub fn to_char(self) -> char {
+        match self {
+            TaskStatus::Open => ' ',
+            TaskStatus::Done => 'x',
+            TaskStatus::InProgress => '/',
+            TaskStatus::Cancelled => '-',
+            TaskStatus::Deferred => '>',
+            TaskStatus::Other(other) => other,
+        }
+    }
+
+    /// The task still needs to be done
+    pub fn is_open(self) -> bool {
+        matches!(self, TaskStatus::Open | TaskStatus::InProgress)
+    }
+}
+
+/// A checkbox list item: `- [ ] text #tag 📅 2024-01-01`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MDTask {
+    pub status: TaskStatus,
+    /// The text after the checkbox
+    pub text: String,
+    /// The whole list item line, without the indentation
+    pub range: MyRange,
+    /// The character between the brackets
+    pub status_range: MyRange,
+    /// Inline tags, without the '#'
+    pub tags: Vec<String>,
+    /// From `📅 2024-01-01` or `due:: 2024-01-01`
+    pub due: Option<NaiveDate>,
+}
+
+impl MDTask {
+    pub fn new(text: &str) -> impl Iterator<Item = MDTask> + '_ {
+        static TASK_RE: Lazy<Regex> = Lazy::new(|| {
+            Regex::new(
+                r"(?m)^[ \t]*(?<item>(?:[-*+]|\d+[.)]) \[(?<status>[^\]\n])\](?<text>[^\n]*))",
+            )
+            .unwrap()
+        });
+
+        let rope = Rope::from_str(text);
+
+        TASK_RE
+            .captures_iter(text)
+            .flat_map(move |captures| {
+                let (item, status, task_text) = (
+                    captures.name("item")?,
+                    captures.name("status")?,
+                    captures.name("text")?,
+                );
+                let task_text = task_text.as_str().trim();
+
+                Some(MDTask {
+                    status: TaskStatus::from_char(status.as_str().chars().next()?),
+                    text: task_text.to_string(),
+                    range: MyRange::from_range(&rope, item.start()..item.end()),
+                    status_range: MyRange::from_range(&rope, status.range()),
+                    tags: task_tags(task_text),
+                    due: due_date(task_text),
+                })
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+}
+
+impl Rangeable for MDTask {
+    fn range(&self) -> &MyRange {
+        &self.range
+    }
+}
+
+fn task_tags(text: &str) -> Vec<String> {
+    static TAG_RE: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"(^|\s)#(?<tag>[a-zA-Z_\-\/][0-9a-zA-Z_\-\/]*)").unwrap());
+
+    TAG_RE
+        .captures_iter(text)
+        .flat_map(|captures| captures.name("tag"))
+        .map(|tag| tag.as_str().to_string())
+        .filter(|tag| tag.chars().any(|c| c.is_alphabetic()))
+        .collect()
+}
+
+fn due_date(text: &str) -> Option<NaiveDate> {
+    static DUE_RE: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"(📅|due::)\s*(?<date>\d{4}-\d{2}-\d{2})").unwrap());
+
+    DUE_RE
+        .captures(text)
+        .and_then(|captures| captures.name("date"))
+        .and_then(|date| NaiveDate::parse_from_str(date.as_str(), "%Y-%m-%d").ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use itertools::Itertools;
+    use tower_lsp::lsp_types::{Position, Range};
+
+    use super::{MDTask, TaskStatus};
+
+    #[test]
+    fn parse_tasks() {
+        let text = "# Heading\n- [ ] write #project/alpha docs 📅 2026-10-20\n  - [x] done [due:: 2026-01-02]\n- not a task\n1. [/] in progress";
+
+        let parsed = MDTask::new(text).collect_vec();
+
+        assert_eq!(
+            parsed.iter().map(|task| task.status).collect_vec(),
+            vec![TaskStatus::Open, TaskStatus::Done, TaskStatus::InProgress]
+        );
+
+        assert_eq!(
+            parsed[0],
+            MDTask {
+                status: TaskStatus::Open,
+                text: "write #project/alpha docs 📅 2026-10-20".into(),
+                range: Range {
+                    start: Position {
+                        line: 1,
+                        character: 0,
+                    },
+                    end: Position {
+                        line: 1,
+                        character: 44,
+                    },
+                }
+                .into(),
+                status_range: Range {
+                    start: Position {
+                        line: 1,
+                        character: 3,
+                    },
+                    end: Position {
+                        line: 1,
+                        character: 4,
+                    },
+                }
+                .into(),
+                tags: vec!["project/alpha".into()],
+                due: NaiveDate::from_ymd_opt(2026, 10, 20),
+            }
+        );
+
+        assert_eq!(parsed[1].range.start.character, 2);
+        assert_eq!(parsed[1].due, NaiveDate::from_ymd_opt(2026, 1, 2));
+        assert_eq!(parsed[2].text, "in progress");
+    }
+}